@@ -0,0 +1,1654 @@
+//! Discord REST API: the models shared by every module, and one async
+//! function per endpoint, each taking the token by value.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+pub use reqwest::StatusCode;
+
+const API_BASE: &str = "https://discord.com/api/v10";
+
+/// Why a request failed. Displays as a message ready to show to the user,
+/// e.g. `"Failed to fetch messages: 403 Forbidden"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// No answer from Discord: DNS, TLS, connection or timeout.
+    Network(String),
+    /// Discord answered with an error status while doing `context`.
+    Status {
+        /// What was being done, e.g. `"Failed to fetch messages"`.
+        context: &'static str,
+        /// The status Discord answered with.
+        status: StatusCode,
+    },
+    /// A 429: the same request may be sent again after `retry_after`.
+    RateLimited {
+        /// What was being done.
+        context: &'static str,
+        /// How long Discord asked to wait, 1s if it didn't say.
+        retry_after: Duration,
+    },
+    /// Anything else, such as a body that doesn't parse.
+    Other(String),
+}
+
+impl ApiError {
+    fn network(error: reqwest::Error) -> Self {
+        ApiError::Network(error.to_string())
+    }
+
+    fn status(context: &'static str, response: &reqwest::Response) -> Self {
+        let status = response.status();
+        if status != StatusCode::TOO_MANY_REQUESTS {
+            return ApiError::Status { context, status };
+        }
+
+        // Seconds, possibly fractional; the body repeats it as `retry_after`
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map_or(Duration::from_secs(1), Duration::from_secs_f64);
+        ApiError::RateLimited {
+            context,
+            retry_after,
+        }
+    }
+
+    /// The HTTP status Discord answered with, if it answered at all.
+    pub fn http_status(&self) -> Option<StatusCode> {
+        match self {
+            ApiError::Status { status, .. } => Some(*status),
+            ApiError::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed if sent again: rate limits,
+    /// network failures and Discord's own server errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Network(_) | ApiError::RateLimited { .. } => true,
+            ApiError::Status { status, .. } => status.is_server_error(),
+            ApiError::Other(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Network(error) => write!(f, "Network error: {}", error),
+            ApiError::Status { context, status } => write!(f, "{}: {}", context, status),
+            ApiError::RateLimited { context, .. } => {
+                write!(f, "{}: {}", context, StatusCode::TOO_MANY_REQUESTS)
+            }
+            ApiError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<ApiError> for String {
+    fn from(error: ApiError) -> Self {
+        error.to_string()
+    }
+}
+
+/// An online status: the user's own, or another user's presence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserStatus {
+    /// Online.
+    Online,
+    /// Idle, away.
+    Idle,
+    /// Do Not Disturb, notifications muted.
+    DoNotDisturb,
+    /// Shown as offline to others.
+    Invisible,
+}
+
+impl UserStatus {
+    /// The status as the API spells it.
+    pub fn as_str(&self) -> &str {
+        match self {
+            UserStatus::Online => "online",
+            UserStatus::Idle => "idle",
+            UserStatus::DoNotDisturb => "dnd",
+            UserStatus::Invisible => "invisible",
+        }
+    }
+
+    /// Maps a gateway presence status, `None` meaning offline.
+    pub fn from_api(status: &str) -> Option<UserStatus> {
+        match status {
+            "online" => Some(UserStatus::Online),
+            "idle" => Some(UserStatus::Idle),
+            "dnd" => Some(UserStatus::DoNotDisturb),
+            "invisible" => Some(UserStatus::Invisible),
+            _ => None,
+        }
+    }
+
+    /// The name shown in the status menu.
+    pub fn display_name(&self) -> &str {
+        match self {
+            UserStatus::Online => "Online",
+            UserStatus::Idle => "Idle",
+            UserStatus::DoNotDisturb => "Do Not Disturb",
+            UserStatus::Invisible => "Invisible",
+        }
+    }
+
+    /// RGB of the status dot.
+    pub fn color(&self) -> [f32; 3] {
+        match self {
+            UserStatus::Online => [0.3, 0.8, 0.3],       // Green
+            UserStatus::Idle => [1.0, 0.7, 0.2],         // Yellow/Orange
+            UserStatus::DoNotDisturb => [0.9, 0.3, 0.3], // Red
+            UserStatus::Invisible => [0.5, 0.5, 0.5],    // Gray
+        }
+    }
+}
+
+/// A Discord user.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct User {
+    /// The user's id.
+    pub id: String,
+    /// Unique username.
+    pub username: String,
+    /// Legacy `#1234` tag, `"0"` for migrated users.
+    pub discriminator: String,
+    /// Avatar hash for the CDN, `None` for the default avatar.
+    #[serde(default)]
+    pub avatar: Option<String>,
+    /// Profile "About me", when the payload includes it.
+    #[serde(default)]
+    pub bio: Option<String>,
+    /// Display name, shown instead of the username when set.
+    #[serde(default)]
+    pub global_name: Option<String>,
+}
+
+/// A guild (server) the user is in.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Guild {
+    /// The guild's id.
+    pub id: String,
+    /// The guild's name.
+    pub name: String,
+    /// Icon hash for the CDN.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Whether the user owns the guild.
+    #[serde(default)]
+    pub owner: bool,
+    /// Our base permissions in the guild, as a stringified bitfield.
+    #[serde(default)]
+    pub permissions: Option<String>,
+}
+
+/// A guild channel, thread, forum post or DM.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Channel {
+    /// The channel's id.
+    pub id: String,
+    /// Discord's channel type: 0 text, 1 DM, 2 voice, 3 group DM, 4
+    /// category, 5 announcement, 10-12 threads, 13 stage, 15 forum.
+    #[serde(rename = "type")]
+    pub channel_type: i32,
+    /// Name, `None` for 1:1 DMs.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Sort order within its category.
+    #[serde(default)]
+    pub position: i32,
+    /// The category, or the parent channel of a thread.
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// The guild, `None` for DMs.
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    /// The other users of a DM.
+    #[serde(default)]
+    pub recipients: Vec<User>,
+    /// READY sends bare ids for DM recipients, resolved against `Ready::users`.
+    #[serde(default, skip_serializing)]
+    pub recipient_ids: Vec<String>,
+    /// Newest message, which orders DMs by activity.
+    #[serde(default)]
+    pub last_message_id: Option<String>,
+    /// Icon hash of a group DM.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Archive and lock state of a thread.
+    #[serde(default)]
+    pub thread_metadata: Option<ThreadMetadata>,
+    /// Messages in a thread, as counted by Discord.
+    #[serde(default)]
+    pub message_count: Option<u32>,
+    /// Our membership, set only for threads we have joined.
+    #[serde(default)]
+    pub member: Option<ThreadMember>,
+    /// Tags a forum channel defines, forum posts reference them.
+    #[serde(default)]
+    pub available_tags: Vec<ForumTag>,
+    /// Ids of the forum tags applied to a forum post.
+    #[serde(default)]
+    pub applied_tags: Vec<String>,
+    /// Voice and stage channels, 0 meaning unlimited.
+    #[serde(default)]
+    pub user_limit: Option<u32>,
+    /// Role and member overwrites of the guild permissions.
+    #[serde(default)]
+    pub permission_overwrites: Vec<PermissionOverwrite>,
+    /// Slowmode, seconds a user has to wait between messages.
+    #[serde(default)]
+    pub rate_limit_per_user: u32,
+    /// Channel topic, shown in the header.
+    #[serde(default)]
+    pub topic: Option<String>,
+    /// Age-restricted channel.
+    #[serde(default)]
+    pub nsfw: bool,
+}
+
+/// A role or member overwrite of a channel's permissions.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PermissionOverwrite {
+    /// Role or user id, depending on the type.
+    pub id: String,
+    /// [`OVERWRITE_ROLE`](crate::permissions::OVERWRITE_ROLE) or
+    /// [`OVERWRITE_MEMBER`](crate::permissions::OVERWRITE_MEMBER).
+    #[serde(rename = "type")]
+    pub overwrite_type: i32,
+    /// Permissions granted, a stringified bitfield.
+    #[serde(default)]
+    pub allow: String,
+    /// Permissions taken away, a stringified bitfield.
+    #[serde(default)]
+    pub deny: String,
+}
+
+impl Channel {
+    /// Threads, forum posts included.
+    pub fn is_thread(&self) -> bool {
+        matches!(self.channel_type, 10..=12)
+    }
+
+    /// Archived threads are read-only until someone posts.
+    pub fn is_archived(&self) -> bool {
+        self.thread_metadata
+            .as_ref()
+            .map(|m| m.archived)
+            .unwrap_or(false)
+    }
+
+    /// Locked threads only accept moderators' messages.
+    pub fn is_locked(&self) -> bool {
+        self.thread_metadata
+            .as_ref()
+            .map(|m| m.locked)
+            .unwrap_or(false)
+    }
+}
+
+/// A tag forum posts can be filed under.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ForumTag {
+    /// The tag's id.
+    pub id: String,
+    /// The tag's name.
+    pub name: String,
+    /// Unicode emoji shown before the name.
+    #[serde(default)]
+    pub emoji_name: Option<String>,
+}
+
+impl ForumTag {
+    /// Emoji and name, as shown on posts.
+    pub fn label(&self) -> String {
+        match &self.emoji_name {
+            Some(emoji) => format!("{} {}", emoji, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Archive and lock state of a thread.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ThreadMetadata {
+    /// Archived, read-only until someone posts.
+    #[serde(default)]
+    pub archived: bool,
+    /// Locked, only moderators can post or unarchive.
+    #[serde(default)]
+    pub locked: bool,
+    /// When it was archived or last changed state.
+    #[serde(default)]
+    pub archive_timestamp: Option<String>,
+}
+
+/// The user's membership of a thread.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ThreadMember {
+    /// Thread id, omitted when embedded in the thread itself.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The member, omitted in some payloads.
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+/// Where a search runs: a whole guild or a single (DM) channel.
+#[derive(Debug, Clone)]
+pub enum SearchScope {
+    /// Guild search by guild id.
+    Guild(String),
+    /// Channel search by channel id.
+    Channel(String),
+}
+
+/// One page of search results.
+#[derive(Debug, Clone)]
+pub struct SearchPage {
+    /// Matches in the whole scope, not just this page.
+    pub total_results: u32,
+    /// This page's hits.
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    total_results: u32,
+    // Each hit comes wrapped in its own array, the hit first
+    #[serde(default)]
+    messages: Vec<Vec<Message>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreadList {
+    #[serde(default)]
+    threads: Vec<Channel>,
+    #[serde(default)]
+    members: Vec<ThreadMember>,
+}
+
+impl ThreadList {
+    /// Attach our membership entries to the threads they belong to.
+    fn into_threads(self) -> Vec<Channel> {
+        let ThreadList {
+            mut threads,
+            members,
+        } = self;
+
+        for thread in &mut threads {
+            if let Some(member) = members
+                .iter()
+                .find(|m| m.id.as_deref() == Some(thread.id.as_str()))
+            {
+                thread.member = Some(member.clone());
+            }
+        }
+
+        threads
+    }
+}
+
+/// A message, with the author and what it mentions.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Message {
+    /// The message's id, a snowflake.
+    pub id: String,
+    /// The channel it was sent in.
+    #[serde(default)]
+    pub channel_id: String,
+    /// The guild, set on gateway messages from guilds.
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    /// Text of the message.
+    pub content: String,
+    /// Who sent it.
+    pub author: User,
+    /// ISO 8601 send time.
+    pub timestamp: String,
+    /// Users it pings.
+    #[serde(default)]
+    pub mentions: Vec<User>,
+    /// Roles it pings.
+    #[serde(default)]
+    pub mention_roles: Vec<String>,
+    /// Whether it pings @everyone or @here.
+    #[serde(default)]
+    pub mention_everyone: bool,
+    /// `MESSAGE_FLAG_*` bits.
+    #[serde(default)]
+    pub flags: u64,
+    /// Whether it is pinned.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Uploaded files.
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Partial member (no user) attached to gateway guild messages.
+    #[serde(default)]
+    pub member: Option<GuildMember>,
+}
+
+impl Message {
+    /// The id as a number, which orders messages by creation time.
+    pub fn snowflake(&self) -> u64 {
+        self.id.parse().unwrap_or(0)
+    }
+}
+
+/// A file uploaded with a message.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Attachment {
+    /// The attachment's id.
+    pub id: String,
+    /// The file's name.
+    pub filename: String,
+    /// CDN URL of the file.
+    pub url: String,
+    /// MIME type, e.g. `image/png`.
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Size in bytes.
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// A guild role.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Role {
+    /// The role's id, the guild's id for @everyone.
+    pub id: String,
+    /// The role's name.
+    pub name: String,
+    /// RGB packed into an integer, 0 meaning no color.
+    #[serde(default)]
+    pub color: u32,
+    /// Higher roles sort first.
+    #[serde(default)]
+    pub position: i32,
+    /// Shown as a separate group in the member list.
+    #[serde(default)]
+    pub hoist: bool,
+    /// Permissions it grants, a stringified bitfield.
+    #[serde(default)]
+    pub permissions: String,
+}
+
+impl Role {
+    /// The color as RGB components in 0..=1, `None` when it has none.
+    pub fn rgb(&self) -> Option<[f32; 3]> {
+        if self.color == 0 {
+            return None;
+        }
+
+        Some([
+            ((self.color >> 16) & 0xff) as f32 / 255.0,
+            ((self.color >> 8) & 0xff) as f32 / 255.0,
+            (self.color & 0xff) as f32 / 255.0,
+        ])
+    }
+}
+
+/// A user's voice connection: the channel and their mute state.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VoiceState {
+    /// Missing inside READY guild payloads, where the guild is implied.
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    /// `None` once the user disconnects.
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    /// The user.
+    pub user_id: String,
+    /// The user as a guild member.
+    #[serde(default)]
+    pub member: Option<GuildMember>,
+    /// Muted by a moderator.
+    #[serde(default)]
+    pub mute: bool,
+    /// Deafened by a moderator.
+    #[serde(default)]
+    pub deaf: bool,
+    /// Muted themselves.
+    #[serde(default)]
+    pub self_mute: bool,
+    /// Deafened themselves.
+    #[serde(default)]
+    pub self_deaf: bool,
+    /// Streaming their screen.
+    #[serde(default)]
+    pub self_stream: bool,
+    /// Camera on.
+    #[serde(default)]
+    pub self_video: bool,
+    /// Stage audience members are suppressed.
+    #[serde(default)]
+    pub suppress: bool,
+}
+
+// Channel notification levels in user guild settings
+/// Notify for every message.
+pub const NOTIFY_ALL_MESSAGES: i32 = 0;
+/// Notify only when mentioned.
+pub const NOTIFY_ONLY_MENTIONS: i32 = 1;
+/// Never notify.
+pub const NOTIFY_NOTHING: i32 = 2;
+/// Use the guild's default.
+pub const NOTIFY_INHERIT: i32 = 3;
+
+fn inherit_notifications() -> i32 {
+    NOTIFY_INHERIT
+}
+
+/// A channel's entry in the user's notification settings.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChannelNotificationOverride {
+    /// The channel it applies to.
+    pub channel_id: String,
+    /// A `NOTIFY_*` level.
+    #[serde(default = "inherit_notifications")]
+    pub message_notifications: i32,
+    /// Whether the channel is muted.
+    #[serde(default)]
+    pub muted: bool,
+}
+
+// Message flags
+/// Published to following channels (announcement channels).
+pub const MESSAGE_FLAG_CROSSPOSTED: u64 = 1 << 0;
+
+// Page sizes for message history
+/// Messages fetched when a channel opens.
+pub const MESSAGE_PAGE: usize = 50;
+/// Most messages Discord returns per request.
+pub const MAX_MESSAGE_PAGE: usize = 100;
+
+/// A guild member; REST pages nest the user, some gateway payloads
+/// only give its id.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GuildMember {
+    /// The user, when nested.
+    #[serde(default)]
+    pub user: Option<User>,
+    /// The user's id, when not nested.
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Nickname in this guild.
+    #[serde(default)]
+    pub nick: Option<String>,
+    /// Ids of the member's roles.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl GuildMember {
+    /// The member's user id, from either form.
+    pub fn id(&self) -> Option<&str> {
+        self.user
+            .as_ref()
+            .map(|u| u.id.as_str())
+            .or(self.user_id.as_deref())
+    }
+}
+
+// Relationship types
+/// A friend.
+pub const RELATIONSHIP_FRIEND: i32 = 1;
+/// A blocked user.
+pub const RELATIONSHIP_BLOCKED: i32 = 2;
+/// A friend request the user received.
+pub const RELATIONSHIP_INCOMING_REQUEST: i32 = 3;
+/// A friend request the user sent.
+pub const RELATIONSHIP_OUTGOING_REQUEST: i32 = 4;
+
+/// The user's relationship with another user.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Relationship {
+    /// Same as the other user's id.
+    pub id: String,
+    /// A `RELATIONSHIP_*` type.
+    #[serde(rename = "type")]
+    pub relationship_type: i32,
+    /// Missing from READY, which only references users by id.
+    #[serde(default)]
+    pub user: Option<User>,
+}
+
+/// A folder of guilds in the guild list.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GuildFolder {
+    /// Guilds in the folder, in order; a lone guild is a folder of one.
+    #[serde(default)]
+    pub guild_ids: Vec<String>,
+    /// The folder's id, `None` for single guilds.
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    /// The folder's name, if it was given one.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// The user's client settings that the app uses.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UserSettings {
+    /// Legacy guild order.
+    #[serde(default)]
+    pub guild_positions: Vec<String>,
+    /// Guild folders and their order.
+    #[serde(default)]
+    pub guild_folders: Vec<GuildFolder>,
+    /// `online`, `idle`, `dnd` or `invisible`.
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SendMessagePayload {
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateDmPayload {
+    recipient_id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FriendRequestPayload {
+    username: String,
+    discriminator: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RelationshipPayload {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    relationship_type: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateThreadPayload {
+    name: String,
+    auto_archive_duration: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct ForumPostPayload {
+    name: String,
+    applied_tags: Vec<String>,
+    message: SendMessagePayload,
+}
+
+#[derive(Debug, Serialize)]
+struct FollowChannelPayload {
+    webhook_channel_id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GuildSettingsPayload {
+    channel_overrides: HashMap<String, ChannelNotificationOverride>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusPayload {
+    status: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ProfileUpdatePayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bio: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    global_name: Option<String>,
+}
+
+/// A user's profile: the user and their bio.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UserProfile {
+    /// The user.
+    pub user: User,
+    /// Profile "About me".
+    #[serde(default)]
+    pub bio: Option<String>,
+}
+
+/// The user the token belongs to; fails with a 401 when it is invalid.
+pub async fn verify_token(token: String) -> Result<User, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/users/@me", API_BASE))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Invalid token", &response));
+    }
+
+    response
+        .json::<User>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse user: {}", e)))
+}
+
+/// The user's client settings: guild order, folders and status.
+pub async fn fetch_user_settings(token: String) -> Result<UserSettings, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/users/@me/settings", API_BASE))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch user settings", &response));
+    }
+
+    response
+        .json::<UserSettings>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse user settings: {}", e)))
+}
+
+/// The user's guilds, in the order of their guild list.
+pub async fn fetch_guilds(token: String) -> Result<Vec<Guild>, ApiError> {
+    let client = reqwest::Client::new();
+
+    // Fetch guilds
+    let guilds_response = client
+        .get(format!("{}/users/@me/guilds", API_BASE))
+        .header("Authorization", token.clone())
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !guilds_response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch guilds", &guilds_response));
+    }
+
+    let mut guilds: Vec<Guild> = guilds_response
+        .json()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse guilds: {}", e)))?;
+
+    // Debug: Print guild info
+    eprintln!("Fetched {} guilds", guilds.len());
+    for guild in &guilds {
+        eprintln!(
+            "Guild: id={}, name='{}', name_len={}, is_empty={}, is_whitespace={}",
+            guild.id,
+            guild.name,
+            guild.name.len(),
+            guild.name.is_empty(),
+            guild.name.trim().is_empty()
+        );
+    }
+
+    // Fetch user settings to get guild order
+    if let Ok(settings) = fetch_user_settings(token).await {
+        eprintln!("Guild positions: {:?}", settings.guild_positions);
+        eprintln!("Guild folders: {:?}", settings.guild_folders);
+
+        // Build ordered list from guild_folders (newer Discord format)
+        let mut ordered_ids: Vec<String> = Vec::new();
+
+        for folder in &settings.guild_folders {
+            ordered_ids.extend(folder.guild_ids.clone());
+        }
+
+        // If guild_folders is empty, fall back to guild_positions
+        if ordered_ids.is_empty() {
+            ordered_ids = settings.guild_positions.clone();
+        }
+
+        eprintln!("Ordered IDs: {:?}", ordered_ids);
+
+        if !ordered_ids.is_empty() {
+            // Sort guilds based on ordered IDs
+            guilds.sort_by_key(|guild| {
+                ordered_ids
+                    .iter()
+                    .position(|id| id == &guild.id)
+                    .unwrap_or(usize::MAX) // Put guilds not in positions at the end
+            });
+        }
+    } else {
+        eprintln!("Failed to fetch user settings, using default order");
+    }
+
+    Ok(guilds)
+}
+
+/// A guild's text, voice, category, announcement, stage and forum
+/// channels, sorted by position. Threads come from
+/// [`fetch_active_threads`].
+pub async fn fetch_channels(token: String, guild_id: String) -> Result<Vec<Channel>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/guilds/{}/channels", API_BASE, guild_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch channels", &response));
+    }
+
+    let mut channels: Vec<Channel> = response
+        .json()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse channels: {}", e)))?;
+
+    // Debug: Print channel info
+    eprintln!("Fetched {} channels", channels.len());
+    for channel in &channels {
+        eprintln!(
+            "Channel: id={}, name={:?}, type={}, position={}, parent_id={:?}",
+            channel.id, channel.name, channel.channel_type, channel.position, channel.parent_id
+        );
+    }
+
+    // Keep text (0), voice (2), categories (4), announcements (5), stages (13)
+    // and forums (15)
+    channels.retain(|c| matches!(c.channel_type, 0 | 2 | 4 | 5 | 13 | 15));
+
+    // Sort by position to maintain Discord's order
+    channels.sort_by_key(|c| c.position);
+
+    eprintln!("Kept {} channels after filtering", channels.len());
+
+    Ok(channels)
+}
+
+/// Any channel, thread or DM we can see, by id.
+pub async fn fetch_channel(token: String, channel_id: String) -> Result<Channel, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/channels/{}", API_BASE, channel_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch channel", &response));
+    }
+
+    response
+        .json::<Channel>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse channel: {}", e)))
+}
+
+/// The guild's active threads, with our membership attached to those we
+/// joined.
+pub async fn fetch_active_threads(
+    token: String,
+    guild_id: String,
+) -> Result<Vec<Channel>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/guilds/{}/threads/active", API_BASE, guild_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch threads", &response));
+    }
+
+    response
+        .json::<ThreadList>()
+        .await
+        .map(ThreadList::into_threads)
+        .map_err(|e| ApiError::Other(format!("Failed to parse threads: {}", e)))
+}
+
+/// One page of search results; `params` come from
+/// [`SearchFilters::to_params`](crate::search::SearchFilters::to_params).
+/// Fails while Discord is still indexing the guild.
+pub async fn search_messages(
+    token: String,
+    scope: SearchScope,
+    params: Vec<(&'static str, String)>,
+) -> Result<SearchPage, ApiError> {
+    let url = match &scope {
+        SearchScope::Guild(guild_id) => format!("{}/guilds/{}/messages/search", API_BASE, guild_id),
+        SearchScope::Channel(channel_id) => {
+            format!("{}/channels/{}/messages/search", API_BASE, channel_id)
+        }
+    };
+
+    let client = reqwest::Client::new();
+    let response = client
+        .get(url)
+        .header("Authorization", token)
+        .query(&params)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    // 202 means Discord is still indexing the guild
+    if response.status() == reqwest::StatusCode::ACCEPTED {
+        return Err(ApiError::Other(
+            "Search index is still being built, try again in a moment".to_string(),
+        ));
+    }
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to search messages", &response));
+    }
+
+    let results = response
+        .json::<SearchResponse>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse search results: {}", e)))?;
+
+    Ok(SearchPage {
+        total_results: results.total_results,
+        messages: results
+            .messages
+            .into_iter()
+            .filter_map(|hit| hit.into_iter().next())
+            .collect(),
+    })
+}
+
+/// The 25 most recently archived public threads of a channel.
+pub async fn fetch_archived_threads(
+    token: String,
+    channel_id: String,
+) -> Result<Vec<Channel>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "{}/channels/{}/threads/archived/public?limit=25",
+            API_BASE, channel_id
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status(
+            "Failed to fetch archived threads",
+            &response,
+        ));
+    }
+
+    response
+        .json::<ThreadList>()
+        .await
+        .map(ThreadList::into_threads)
+        .map_err(|e| ApiError::Other(format!("Failed to parse archived threads: {}", e)))
+}
+
+/// Starts a thread from a message, named `name`.
+pub async fn create_thread_from_message(
+    token: String,
+    channel_id: String,
+    message_id: String,
+    name: String,
+) -> Result<Channel, ApiError> {
+    let client = reqwest::Client::new();
+    let payload = CreateThreadPayload {
+        name,
+        auto_archive_duration: 1440,
+    };
+
+    let response = client
+        .post(format!(
+            "{}/channels/{}/messages/{}/threads",
+            API_BASE, channel_id, message_id
+        ))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to create thread", &response));
+    }
+
+    response
+        .json::<Channel>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse thread: {}", e)))
+}
+
+/// Posts to a forum: a new thread titled `title` with the given tags,
+/// opened by a message with `content`.
+pub async fn create_forum_post(
+    token: String,
+    forum_id: String,
+    title: String,
+    tags: Vec<String>,
+    content: String,
+) -> Result<Channel, ApiError> {
+    let client = reqwest::Client::new();
+    let payload = ForumPostPayload {
+        name: title,
+        applied_tags: tags,
+        message: SendMessagePayload { content },
+    };
+
+    let response = client
+        .post(format!("{}/channels/{}/threads", API_BASE, forum_id))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to create post", &response));
+    }
+
+    response
+        .json::<Channel>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse post: {}", e)))
+}
+
+/// Joins a thread.
+pub async fn join_thread(token: String, thread_id: String) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .put(format!(
+            "{}/channels/{}/thread-members/@me",
+            API_BASE, thread_id
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to join thread", &response));
+    }
+
+    Ok(())
+}
+
+/// Leaves a thread.
+pub async fn leave_thread(token: String, thread_id: String) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .delete(format!(
+            "{}/channels/{}/thread-members/@me",
+            API_BASE, thread_id
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to leave thread", &response));
+    }
+
+    Ok(())
+}
+
+/// The user's direct and group DM channels.
+pub async fn fetch_dm_channels(token: String) -> Result<Vec<Channel>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/users/@me/channels", API_BASE))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status(
+            "Failed to fetch direct messages",
+            &response,
+        ));
+    }
+
+    let mut channels: Vec<Channel> = response
+        .json()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse direct messages: {}", e)))?;
+
+    // Keep DMs (1) and group DMs (3)
+    channels.retain(|c| c.channel_type == 1 || c.channel_type == 3);
+
+    Ok(channels)
+}
+
+/// Opens (or finds) the DM channel with a user.
+pub async fn create_dm(token: String, recipient_id: String) -> Result<Channel, ApiError> {
+    let client = reqwest::Client::new();
+    let payload = CreateDmPayload { recipient_id };
+
+    let response = client
+        .post(format!("{}/users/@me/channels", API_BASE))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to open direct message", &response));
+    }
+
+    response
+        .json::<Channel>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse direct message: {}", e)))
+}
+
+/// Friends, blocks and pending friend requests.
+pub async fn fetch_relationships(token: String) -> Result<Vec<Relationship>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/users/@me/relationships", API_BASE))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch friends", &response));
+    }
+
+    response
+        .json::<Vec<Relationship>>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse friends: {}", e)))
+}
+
+/// Sends a friend request by username.
+pub async fn send_friend_request(token: String, username: String) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let payload = FriendRequestPayload {
+        username,
+        discriminator: None,
+    };
+
+    let response = client
+        .post(format!("{}/users/@me/relationships", API_BASE))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to send friend request", &response));
+    }
+
+    Ok(())
+}
+
+/// Accepts a pending request when `relationship_type` is `None`, otherwise
+/// sets the relationship explicitly (e.g. `RELATIONSHIP_BLOCKED`).
+pub async fn put_relationship(
+    token: String,
+    user_id: String,
+    relationship_type: Option<i32>,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let payload = RelationshipPayload { relationship_type };
+
+    let response = client
+        .put(format!("{}/users/@me/relationships/{}", API_BASE, user_id))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to update relationship", &response));
+    }
+
+    Ok(())
+}
+
+/// Removes a friend, declines or cancels a request, or unblocks a user.
+pub async fn delete_relationship(token: String, user_id: String) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .delete(format!("{}/users/@me/relationships/{}", API_BASE, user_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to update relationship", &response));
+    }
+
+    Ok(())
+}
+
+/// CDN url for a user's avatar, if they have set one.
+pub fn avatar_url(user: &User) -> Option<String> {
+    user.avatar.as_ref().map(|hash| {
+        format!(
+            "https://cdn.discordapp.com/avatars/{}/{}.png?size=32",
+            user.id, hash
+        )
+    })
+}
+
+/// CDN url for a group DM's icon, if it has one.
+pub fn channel_icon_url(channel: &Channel) -> Option<String> {
+    channel.icon.as_ref().map(|hash| {
+        format!(
+            "https://cdn.discordapp.com/channel-icons/{}/{}.png?size=32",
+            channel.id, hash
+        )
+    })
+}
+
+/// Downloads an image, e.g. an avatar from the CDN. No token needed.
+pub async fn fetch_image(url: String) -> Result<Vec<u8>, ApiError> {
+    let response = reqwest::get(url).await.map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch image", &response));
+    }
+
+    response
+        .bytes()
+        .await
+        .map(|bytes| bytes.to_vec())
+        .map_err(|e| ApiError::Other(format!("Failed to read image: {}", e)))
+}
+
+/// The newest page of a channel's messages, oldest first.
+pub async fn fetch_messages(token: String, channel_id: String) -> Result<Vec<Message>, ApiError> {
+    fetch_messages_with_query(token, channel_id, format!("limit={}", MESSAGE_PAGE)).await
+}
+
+/// The page of messages around `message_id`, for jumping to it.
+pub async fn fetch_messages_around(
+    token: String,
+    channel_id: String,
+    message_id: String,
+) -> Result<Vec<Message>, ApiError> {
+    fetch_messages_with_query(
+        token,
+        channel_id,
+        format!("limit={}&around={}", MESSAGE_PAGE, message_id),
+    )
+    .await
+}
+
+/// The full page of messages right before `message_id`.
+pub async fn fetch_messages_before(
+    token: String,
+    channel_id: String,
+    message_id: String,
+) -> Result<Vec<Message>, ApiError> {
+    fetch_messages_with_query(
+        token,
+        channel_id,
+        format!("limit={}&before={}", MAX_MESSAGE_PAGE, message_id),
+    )
+    .await
+}
+
+/// The full page of messages right after `message_id`.
+pub async fn fetch_messages_after(
+    token: String,
+    channel_id: String,
+    message_id: String,
+) -> Result<Vec<Message>, ApiError> {
+    fetch_messages_with_query(
+        token,
+        channel_id,
+        format!("limit={}&after={}", MAX_MESSAGE_PAGE, message_id),
+    )
+    .await
+}
+
+async fn fetch_messages_with_query(
+    token: String,
+    channel_id: String,
+    query: String,
+) -> Result<Vec<Message>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "{}/channels/{}/messages?{}",
+            API_BASE, channel_id, query
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch messages", &response));
+    }
+
+    let mut messages: Vec<Message> = response
+        .json()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse messages: {}", e)))?;
+
+    // Discord returns messages in reverse chronological order, so reverse them
+    messages.reverse();
+
+    Ok(messages)
+}
+
+/// Publishes a message in an announcement channel to every following channel.
+pub async fn crosspost_message(
+    token: String,
+    channel_id: String,
+    message_id: String,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!(
+            "{}/channels/{}/messages/{}/crosspost",
+            API_BASE, channel_id, message_id
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to publish message", &response));
+    }
+
+    Ok(())
+}
+
+/// Follows an announcement channel into `target_channel_id`, where its
+/// posts will be crossposted.
+pub async fn follow_channel(
+    token: String,
+    channel_id: String,
+    target_channel_id: String,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let payload = FollowChannelPayload {
+        webhook_channel_id: target_channel_id,
+    };
+
+    let response = client
+        .post(format!("{}/channels/{}/followers", API_BASE, channel_id))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to follow channel", &response));
+    }
+
+    Ok(())
+}
+
+/// Saves a channel's notification setting in the guild's settings.
+pub async fn update_channel_notifications(
+    token: String,
+    guild_id: String,
+    channel_override: ChannelNotificationOverride,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let payload = GuildSettingsPayload {
+        channel_overrides: HashMap::from([(channel_override.channel_id.clone(), channel_override)]),
+    };
+
+    let response = client
+        .patch(format!(
+            "{}/users/@me/guilds/{}/settings",
+            API_BASE, guild_id
+        ))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status(
+            "Failed to update notification settings",
+            &response,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Recent messages that mention the user or their roles.
+pub async fn fetch_mentions(token: String) -> Result<Vec<Message>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!(
+            "{}/users/@me/mentions?limit=25&roles=true&everyone=false",
+            API_BASE
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch mentions", &response));
+    }
+
+    response
+        .json::<Vec<Message>>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse mentions: {}", e)))
+}
+
+/// A channel's pinned messages.
+pub async fn fetch_pins(token: String, channel_id: String) -> Result<Vec<Message>, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/channels/{}/pins", API_BASE, channel_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch pins", &response));
+    }
+
+    response
+        .json::<Vec<Message>>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse pins: {}", e)))
+}
+
+/// Pins a message.
+pub async fn pin_message(
+    token: String,
+    channel_id: String,
+    message_id: String,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .put(format!(
+            "{}/channels/{}/pins/{}",
+            API_BASE, channel_id, message_id
+        ))
+        .header("Authorization", token)
+        .header("Content-Length", "0")
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to pin message", &response));
+    }
+
+    Ok(())
+}
+
+/// Unpins a message.
+pub async fn unpin_message(
+    token: String,
+    channel_id: String,
+    message_id: String,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .delete(format!(
+            "{}/channels/{}/pins/{}",
+            API_BASE, channel_id, message_id
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to unpin message", &response));
+    }
+
+    Ok(())
+}
+
+/// Removes a message from the mentions inbox.
+pub async fn delete_mention(token: String, message_id: String) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .delete(format!("{}/users/@me/mentions/{}", API_BASE, message_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status(
+            "Failed to mark mention as read",
+            &response,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sends a text message to a channel.
+pub async fn send_message(
+    token: String,
+    channel_id: String,
+    content: String,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let payload = SendMessagePayload { content };
+
+    let response = client
+        .post(format!("{}/channels/{}/messages", API_BASE, channel_id))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to send message", &response));
+    }
+
+    Ok(())
+}
+
+/// Changes the user's bio and display name; `None` leaves a field as is.
+pub async fn update_profile(
+    token: String,
+    bio: Option<String>,
+    display_name: Option<String>,
+) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let payload = ProfileUpdatePayload {
+        bio,
+        global_name: display_name,
+    };
+
+    let response = client
+        .patch(format!("{}/users/@me", API_BASE))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to update profile", &response));
+    }
+
+    Ok(())
+}
+
+/// A user's profile, for the profile popup.
+pub async fn fetch_user_profile(token: String, user_id: String) -> Result<UserProfile, ApiError> {
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}/users/{}/profile", API_BASE, user_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to fetch user profile", &response));
+    }
+
+    response
+        .json::<UserProfile>()
+        .await
+        .map_err(|e| ApiError::Other(format!("Failed to parse user profile: {}", e)))
+}
+
+/// Sets the user's online status.
+pub async fn set_status(token: String, status: UserStatus) -> Result<(), ApiError> {
+    let client = reqwest::Client::new();
+    let payload = StatusPayload {
+        status: status.as_str().to_string(),
+    };
+
+    let response = client
+        .patch(format!("{}/users/@me/settings", API_BASE))
+        .header("Authorization", token)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(ApiError::network)?;
+
+    if !response.status().is_success() {
+        return Err(ApiError::status("Failed to change status", &response));
+    }
+
+    Ok(())
+}
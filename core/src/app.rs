@@ -0,0 +1,2120 @@
+//! The application model shared by the frontends: every user action and
+//! every result as a [`Message`], and [`App::update`], which applies them
+//! to the [`AppState`] and returns an [`Effect`] with the work to start.
+
+use crate::state::{self, AppState};
+use crate::{api, export, gateway, search, store};
+use api::ApiError;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A client session: the state every frontend draws, plus the local stores
+/// behind it.
+pub struct App {
+    /// What the frontends draw.
+    pub state: AppState,
+    // Local cache and message index of the logged in (or last) account
+    store: Option<store::Store>,
+    // In-memory search indexes of opened archives, by archive id
+    archive_stores: HashMap<String, store::Store>,
+}
+
+/// Everything that can happen to the client: user actions, finished
+/// requests and gateway events.
+#[derive(Debug, Clone)]
+pub enum Message {
+    // Login
+    /// The login field was edited.
+    TokenInputChanged(String),
+    /// Log in with the typed token.
+    Login,
+    /// The token was checked.
+    LoginResult(Result<api::User, ApiError>),
+    /// Browse the cached account without logging in.
+    BrowseOffline,
+    /// Leave offline browsing for the login view.
+    ShowLogin,
+
+    // Archives
+    /// The open export field was edited.
+    ArchivePathInputChanged(String),
+    /// Open the typed export for browsing.
+    OpenArchive,
+
+    // Guild & Channel selection
+    /// The guild list arrived.
+    GuildsLoaded(Result<Vec<api::Guild>, ApiError>),
+    /// A guild (or opened export) was picked, by id.
+    SelectGuild(String),
+    /// The selected guild's channels arrived.
+    ChannelsLoaded(Result<Vec<api::Channel>, ApiError>),
+    /// A channel was picked, by id.
+    SelectChannel(String),
+
+    // Messages
+    /// The newest page of the channel with this id arrived.
+    MessagesLoaded(String, Result<Vec<api::Message>, ApiError>),
+    /// The page around a jumped-to message of the channel with this id
+    /// arrived.
+    MessagesAroundLoaded(String, Result<Vec<api::Message>, ApiError>),
+    /// The composer was edited.
+    MessageInputChanged(String),
+    /// Send the composer's text to the selected channel.
+    SendMessage,
+    /// A message was sent to the channel with this id.
+    MessageSent(String, Result<(), ApiError>),
+
+    // Message history
+    /// Fetch the next page of the newest gap.
+    BackfillGap,
+    /// A page fetched to fill a gap of a channel arrived.
+    BackfillLoaded(String, store::Gap, Result<Vec<api::Message>, ApiError>),
+    /// Messages of a channel newer than the given id arrived.
+    CatchUpLoaded(String, u64, Result<Vec<api::Message>, ApiError>),
+
+    // Status
+    /// Open or close the status menu.
+    ToggleStatusMenu,
+    /// A status was picked.
+    ChangeStatus(state::UserStatus),
+    /// The status was saved.
+    StatusChanged(Result<(), ApiError>),
+
+    // Profile
+    /// Open the profile editor.
+    OpenProfileEditor,
+    /// Close the profile editor.
+    CloseProfileEditor,
+    /// The bio field was edited.
+    ProfileBioInputChanged(String),
+    /// The display name field was edited.
+    ProfileDisplayNameInputChanged(String),
+    /// Save the profile editor's fields.
+    SaveProfile,
+    /// The profile was saved.
+    ProfileSaved(Result<(), ApiError>),
+    /// Show a user's profile, by user id.
+    ViewUserProfile(String),
+    /// A user's profile arrived.
+    UserProfileLoaded(Result<api::UserProfile, ApiError>),
+    /// Close the profile popup.
+    CloseUserProfile,
+
+    // Direct messages
+    /// Show the DM list.
+    SelectDirectMessages,
+    /// The DM channels arrived.
+    DmChannelsLoaded(Result<Vec<api::Channel>, ApiError>),
+    /// Open the DM with a user, by user id.
+    OpenDirectMessage(String),
+    /// The DM channel was opened.
+    DmOpened(Result<api::Channel, ApiError>),
+    /// A DM avatar arrived, keyed like [`AppState::avatars`].
+    AvatarLoaded(String, Result<Vec<u8>, ApiError>),
+
+    // Friends
+    /// Open the friends page.
+    ShowFriends,
+    /// Friends, blocks and requests arrived.
+    RelationshipsLoaded(Result<Vec<api::Relationship>, ApiError>),
+    /// A friends page tab was picked.
+    SelectFriendsTab(state::FriendsTab),
+    /// The add friend field was edited.
+    FriendRequestInputChanged(String),
+    /// Send a friend request to the typed username.
+    SendFriendRequest,
+    /// Accept the request of a user, by user id.
+    AcceptFriendRequest(String),
+    /// Unfriend, unblock or cancel a request, by user id.
+    RemoveRelationship(String),
+    /// Block a user, by user id.
+    BlockUser(String),
+    /// A relationship change was saved.
+    RelationshipUpdated(Result<(), ApiError>),
+
+    // Mentions inbox
+    /// Open or close the mentions inbox.
+    ToggleMentions,
+    /// The mentions inbox arrived.
+    MentionsLoaded(Result<Vec<api::Message>, ApiError>),
+    /// Open a channel at a message: channel id, then message id.
+    JumpToMessage(String, String),
+    /// Remove a message from the inbox, by message id.
+    MarkMentionRead(String),
+    /// A message was removed from the inbox.
+    MentionRemoved(Result<(), ApiError>),
+
+    // Blocked users
+    /// Reveal or hide a run of blocked messages, by the ids of its messages.
+    ToggleBlockedMessages(Vec<String>),
+
+    // Announcements
+    /// Publish an announcement to following channels, by message id.
+    PublishMessage(String),
+    /// The message with this id was published.
+    MessagePublished(String, Result<(), ApiError>),
+    /// Open or close the follow picker.
+    ToggleFollowPicker,
+    /// A guild to follow into was picked, by id.
+    SelectFollowGuild(String),
+    /// The picked guild's channels arrived.
+    FollowChannelsLoaded(Result<Vec<api::Channel>, ApiError>),
+    /// Follow the selected channel into a channel, by id.
+    FollowChannel(String),
+    /// The channel was followed.
+    ChannelFollowed(Result<(), ApiError>),
+
+    // Forums
+    /// A forum sort order was picked.
+    SetForumSort(state::ForumSort),
+    /// A forum tag to filter by was picked, `None` for all.
+    SetForumTagFilter(Option<String>),
+    /// Open or close the new post form.
+    ToggleNewPostForm,
+    /// The post title field was edited.
+    NewPostTitleChanged(String),
+    /// The post message field was edited.
+    NewPostContentChanged(String),
+    /// Add or remove a tag of the new post, by tag id.
+    ToggleNewPostTag(String),
+    /// Post the new post form.
+    CreateForumPost,
+    /// The forum post was created.
+    ForumPostCreated(Result<api::Channel, ApiError>),
+
+    // Threads
+    /// The guild's active threads arrived.
+    ThreadsLoaded(Result<Vec<api::Channel>, ApiError>),
+    /// A channel's archived threads arrived.
+    ArchivedThreadsLoaded(Result<Vec<api::Channel>, ApiError>),
+    /// Open a thread in the side panel, by id.
+    OpenThread(String),
+    /// Close the side panel.
+    CloseThread,
+    /// The open thread's messages arrived.
+    ThreadMessagesLoaded(Result<Vec<api::Message>, ApiError>),
+    /// The thread composer was edited.
+    ThreadInputChanged(String),
+    /// Send the thread composer's text.
+    SendThreadMessage,
+    /// A message was sent to the open thread.
+    ThreadMessageSent(Result<(), ApiError>),
+    /// Start a thread from a message, by message id.
+    StartThreadFromMessage(String),
+    /// Cancel starting a thread.
+    CancelThreadCreation,
+    /// The thread name field was edited.
+    ThreadNameInputChanged(String),
+    /// Create the thread being started.
+    CreateThread,
+    /// The thread was created.
+    ThreadCreated(Result<api::Channel, ApiError>),
+    /// Join a thread, by id.
+    JoinThread(String),
+    /// Leave a thread, by id.
+    LeaveThread(String),
+    /// A thread was joined or left.
+    ThreadMembershipChanged(Result<(), ApiError>),
+
+    // Channel list
+    /// Show or hide channels without View Channel.
+    ToggleLockedChannels,
+
+    // Search
+    /// Open or close the search panel.
+    ToggleSearch,
+    /// Switch between Discord's search and the local index.
+    ToggleSearchLocal,
+    /// The search field was edited.
+    SearchInputChanged(String),
+    /// Search for the typed query.
+    RunSearch,
+    /// Show the results starting at this offset.
+    ChangeSearchPage(u32),
+    /// A page of search results arrived.
+    SearchResultsLoaded(Result<api::SearchPage, ApiError>),
+
+    // Channel header
+    /// Open or close a popover of the channel header.
+    ToggleHeaderPopover(state::HeaderPopover),
+    /// Expand or collapse the channel topic.
+    ToggleTopic,
+    /// The selected channel's pins arrived.
+    PinsLoaded(Result<Vec<api::Message>, ApiError>),
+    /// Pin a message, by id.
+    PinMessage(String),
+    /// Unpin a message, by id.
+    UnpinMessage(String),
+    /// A message was pinned (`true`) or unpinned.
+    PinChanged(String, bool, Result<(), ApiError>),
+    /// A `NOTIFY_*` level was picked for the selected channel.
+    SetChannelNotifications(i32),
+    /// Mute or unmute the selected channel.
+    ToggleChannelMute,
+    /// A channel's notification setting was saved.
+    ChannelNotificationsUpdated(api::ChannelNotificationOverride, Result<(), ApiError>),
+
+    // Export
+    /// Add or remove an export format.
+    ToggleExportFormat(export::ExportFormat),
+    /// The export's first day was edited.
+    ExportSinceChanged(String),
+    /// The export's last day was edited.
+    ExportUntilChanged(String),
+    /// The export directory was edited.
+    ExportDirectoryChanged(String),
+    /// Export the channel of the export popover.
+    StartExport,
+    /// A progress report of the running export.
+    ExportProgress(export::Progress),
+
+    // Member list
+    /// Show or hide the member list.
+    ToggleMemberList,
+    /// The member list scrolled; the relative offset, 0 at the top and 1 at
+    /// the bottom.
+    MemberListScrolled(f32),
+
+    // Gateway
+    /// A gateway event.
+    Gateway(gateway::Event),
+    /// A timer tick, while slowmode cooldowns run.
+    Tick(Instant),
+}
+
+impl Message {
+    /// Messages that only touch local state, the rest are dropped while
+    /// browsing offline since they need the API.
+    fn works_offline(&self) -> bool {
+        matches!(
+            self,
+            Message::TokenInputChanged(_)
+                | Message::Login
+                | Message::LoginResult(_)
+                | Message::BrowseOffline
+                | Message::ShowLogin
+                | Message::ArchivePathInputChanged(_)
+                | Message::OpenArchive
+                | Message::JumpToMessage(..)
+                | Message::SelectGuild(_)
+                | Message::SelectChannel(_)
+                | Message::SelectDirectMessages
+                | Message::ToggleBlockedMessages(_)
+                | Message::SetForumSort(_)
+                | Message::SetForumTagFilter(_)
+                | Message::ToggleLockedChannels
+                | Message::ToggleSearch
+                | Message::SearchInputChanged(_)
+                | Message::RunSearch
+                | Message::ChangeSearchPage(_)
+                | Message::ToggleTopic
+                | Message::Tick(_)
+        )
+    }
+}
+
+/// Work started by an update: futures that each resolve to the next
+/// message for [`App::update`]. Frontends hand them to their own runtime.
+#[must_use]
+pub struct Effect {
+    futures: Vec<BoxFuture<'static, Message>>,
+}
+
+impl Effect {
+    /// Nothing to run.
+    pub fn none() -> Effect {
+        Effect {
+            futures: Vec::new(),
+        }
+    }
+
+    /// Runs `future`, then turns its output into a message with `map`.
+    pub fn perform<T>(
+        future: impl Future<Output = T> + Send + 'static,
+        map: impl FnOnce(T) -> Message + Send + 'static,
+    ) -> Effect {
+        Effect {
+            futures: vec![future.map(map).boxed()],
+        }
+    }
+
+    /// Every future of the given effects, run concurrently.
+    pub fn batch(effects: impl IntoIterator<Item = Effect>) -> Effect {
+        Effect {
+            futures: effects.into_iter().flat_map(|e| e.futures).collect(),
+        }
+    }
+
+    /// Whether there is nothing to run.
+    pub fn is_none(&self) -> bool {
+        self.futures.is_empty()
+    }
+
+    /// The futures to spawn, in no particular order.
+    pub fn into_futures(self) -> Vec<BoxFuture<'static, Message>> {
+        self.futures
+    }
+}
+
+impl fmt::Debug for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Effect")
+            .field("futures", &self.futures.len())
+            .finish()
+    }
+}
+
+impl App {
+    /// Reopens the last account's store so the login screen can offer to
+    /// browse it offline.
+    pub fn new() -> App {
+        let store = store::Store::open_last();
+        let mut state = AppState::new();
+        state.cached_account = store.as_ref().and_then(|store| store.load_account());
+
+        App {
+            state,
+            store,
+            archive_stores: HashMap::new(),
+        }
+    }
+
+    /// Write to the local cache. A failure only means staler data next
+    /// time, so it's logged rather than shown.
+    fn save_to_cache(&mut self, save: impl FnOnce(&mut store::Store) -> Result<(), String>) {
+        if let Some(store) = &mut self.store {
+            if let Err(e) = save(store) {
+                eprintln!("Failed to update cache: {}", e);
+            }
+        }
+    }
+
+    /// Persist what READY delivered: channels of every guild, DMs, users
+    /// and the members that came with it.
+    fn cache_ready(&mut self) {
+        let mut guild_channels: HashMap<String, Vec<api::Channel>> = HashMap::new();
+        for channel in self.state.channel_cache.values() {
+            if let Some(guild_id) = &channel.guild_id {
+                guild_channels
+                    .entry(guild_id.clone())
+                    .or_default()
+                    .push(channel.clone());
+            }
+        }
+        let dm_channels = self.state.dm_channels.clone();
+        let users: Vec<api::User> = self.state.user_cache.values().cloned().collect();
+        let members = self.state.guild_members.clone();
+
+        self.save_to_cache(|store| {
+            for (guild_id, channels) in &guild_channels {
+                store.save_channels(Some(guild_id), channels)?;
+            }
+            for (guild_id, members) in &members {
+                store.save_members(guild_id, members.values())?;
+            }
+            store.save_channels(None, &dm_channels)?;
+            store.save_users(&users)
+        });
+    }
+
+    /// Show a fetched page of the selected channel and record it as a
+    /// range without holes. The latest page also reaches the present, so
+    /// live messages continue it, and a short one reaches the start.
+    fn show_messages(&mut self, messages: Vec<api::Message>, latest: bool) -> Effect {
+        self.index_messages(&messages);
+
+        if let (Some(first), Some(last)) = (messages.first(), messages.last()) {
+            let channel_id = last.channel_id.clone();
+            let start = if latest && messages.len() < api::MESSAGE_PAGE {
+                0
+            } else {
+                first.snowflake()
+            };
+            let end = last.snowflake();
+            self.save_to_cache(|store| store.add_range(&channel_id, start, end));
+            if latest {
+                self.state.live_channels.insert(channel_id, end);
+            }
+        }
+
+        self.state.messages = messages;
+        self.state.error = None;
+        self.request_missing_members();
+
+        self.state.backfill_budget = state::BACKFILL_PAGES;
+        self.backfill()
+    }
+
+    /// Show a guild's channels, cached ones first, and leave whatever was
+    /// open before.
+    fn select_guild(&mut self, guild_id: String) -> Effect {
+        self.state.viewing_dms = false;
+        self.state.show_friends = false;
+        self.state.selected_guild = Some(guild_id.clone());
+        self.state.selected_channel = None;
+        self.state.messages.clear();
+        self.state.open_thread = None;
+        self.state.reset_member_list();
+
+        if let Some(archive) = self.state.archive(&guild_id) {
+            self.state.channels = archive.channels.clone();
+            return Effect::none();
+        }
+        if let Some(store) = &self.store {
+            self.state.channels = store.load_channels(&guild_id);
+        }
+        if self.state.offline {
+            return Effect::none();
+        }
+
+        let token = self.state.token.clone().unwrap();
+        Effect::batch([
+            Effect::perform(
+                api::fetch_channels(token.clone(), guild_id.clone()),
+                Message::ChannelsLoaded,
+            ),
+            Effect::perform(
+                api::fetch_active_threads(token, guild_id),
+                Message::ThreadsLoaded,
+            ),
+        ])
+    }
+
+    fn select_direct_messages(&mut self) -> Effect {
+        self.state.viewing_dms = true;
+        self.state.selected_guild = None;
+        self.state.selected_channel = None;
+        self.state.messages.clear();
+        if self.state.offline {
+            return Effect::none();
+        }
+
+        let token = self.state.token.clone().unwrap();
+        Effect::perform(api::fetch_dm_channels(token), Message::DmChannelsLoaded)
+    }
+
+    /// Select a channel of the current guild or the DM list, dropping what
+    /// belonged to the previous one, and show its cached history. False
+    /// when there is nothing to fetch: an archive, a locked channel or
+    /// offline browsing.
+    fn enter_channel(&mut self, channel_id: &str) -> bool {
+        self.state.show_friends = false;
+        self.state.selected_channel = Some(channel_id.to_string());
+        self.state.open_thread = None;
+        self.state.creating_thread_from = None;
+        self.state.forum_tag_filter = None;
+        self.state.show_new_post = false;
+        self.state.follow_picker_open = false;
+        self.state.header_popover = None;
+        self.state.topic_expanded = false;
+        self.state.messages.clear();
+        self.state.reset_member_list();
+        self.subscribe_member_list();
+
+        if let Some(archive) = self.state.selected_archive() {
+            self.state.messages = archive
+                .messages
+                .get(channel_id)
+                .cloned()
+                .unwrap_or_default();
+            self.state.gaps.clear();
+            return false;
+        }
+
+        // Nothing to fetch, chat_view shows the locked state
+        if let Some(channel) = self.state.selected_channel_info() {
+            if !self.state.can_view(channel) {
+                return false;
+            }
+        }
+
+        // Cached history shows until the fetch replaces it
+        if let Some(store) = &self.store {
+            self.state.messages = store.load_messages(channel_id, api::MESSAGE_PAGE as u32);
+        }
+        self.refresh_gaps();
+        !self.state.offline
+    }
+
+    fn refresh_gaps(&mut self) {
+        self.state.gaps = match (&self.store, &self.state.selected_channel) {
+            (Some(store), Some(channel_id)) => store.gaps(channel_id),
+            _ => Vec::new(),
+        };
+    }
+
+    /// Fill the newest gap in view from its newer side, one page at a time
+    /// while the budget lasts, so the history above grows without holes.
+    fn backfill(&mut self) -> Effect {
+        self.refresh_gaps();
+        if self.state.offline || self.state.backfilling || self.state.backfill_budget == 0 {
+            return Effect::none();
+        }
+
+        let (Some(gap), Some(channel_id)) = (
+            self.state.visible_gap(),
+            self.state.selected_channel.clone(),
+        ) else {
+            return Effect::none();
+        };
+
+        self.state.backfilling = true;
+        self.state.backfill_budget -= 1;
+        let token = self.state.token.clone().unwrap();
+        Effect::perform(
+            api::fetch_messages_before(token, channel_id.clone(), gap.before.to_string()),
+            move |result| Message::BackfillLoaded(channel_id, gap, result),
+        )
+    }
+
+    /// After a reconnect, fetch what was sent while we were away in every
+    /// channel that was up to date, continuing from the last message seen.
+    fn catch_up(&mut self) -> Effect {
+        let live_channels = std::mem::take(&mut self.state.live_channels);
+        Effect::batch(
+            live_channels
+                .into_iter()
+                .map(|(channel_id, after)| self.catch_up_channel(channel_id, after)),
+        )
+    }
+
+    fn catch_up_channel(&self, channel_id: String, after: u64) -> Effect {
+        let token = self.state.token.clone().unwrap();
+        Effect::perform(
+            api::fetch_messages_after(token, channel_id.clone(), after.to_string()),
+            move |result| Message::CatchUpLoaded(channel_id, after, result),
+        )
+    }
+
+    /// A live message continues the channel's range while we're connected.
+    fn record_live_message(&mut self, message: &api::Message) {
+        let id = message.snowflake();
+        if let Some(newest) = self.state.live_channels.get_mut(&message.channel_id) {
+            let start = *newest;
+            *newest = id.max(start);
+            let channel_id = message.channel_id.clone();
+            self.save_to_cache(|store| store.add_range(&channel_id, start, id));
+        }
+        if self.state.selected_channel.as_ref() == Some(&message.channel_id) {
+            self.refresh_gaps();
+        }
+    }
+
+    /// Show the account's cached guilds, DMs and members right away.
+    fn hydrate(&mut self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        match store.load_cache() {
+            Ok(cache) => self.state.hydrate(cache),
+            Err(e) => eprintln!("Failed to load cache: {}", e),
+        }
+    }
+
+    /// Add messages to the local index. REST messages lack `guild_id`, so
+    /// it's filled in from the channel first.
+    fn index_messages(&mut self, messages: &[api::Message]) {
+        let Some(store) = &mut self.store else {
+            return;
+        };
+
+        let messages: Vec<api::Message> = messages
+            .iter()
+            .cloned()
+            .map(|mut message| {
+                if message.guild_id.is_none() {
+                    message.guild_id = self.state.guild_for_channel(&message.channel_id);
+                }
+                message
+            })
+            .collect();
+
+        if let Err(e) = store.save_messages(&messages) {
+            eprintln!("Failed to index messages: {}", e);
+        }
+    }
+
+    /// Run the current search at the given result offset. Server searches
+    /// cover the guild or, for DMs, the conversation; local ones everything.
+    fn search_command(&mut self, offset: u32) -> Effect {
+        if self.state.search_local || self.state.offline || self.state.viewing_archive() {
+            // Archives are searched on their own
+            let store = match self.state.selected_archive() {
+                Some(archive) => self.archive_stores.get(&archive.id),
+                None => self.store.as_ref(),
+            };
+            let (Some(store), Some(filters)) = (store, &self.state.search_filters) else {
+                return Effect::none();
+            };
+
+            match store.search(filters, offset, search::SEARCH_PAGE_SIZE) {
+                Ok((total, messages)) => {
+                    self.state.search_offset = offset;
+                    self.state.search_total = total;
+                    self.state.search_results = messages;
+                    self.state.error = None;
+                }
+                Err(e) => self.state.error = Some(format!("Failed to search: {}", e)),
+            }
+            return Effect::none();
+        }
+
+        let scope = if self.state.viewing_dms {
+            self.state
+                .selected_channel
+                .clone()
+                .map(api::SearchScope::Channel)
+        } else {
+            self.state
+                .selected_guild
+                .clone()
+                .map(api::SearchScope::Guild)
+        };
+        let (Some(scope), Some(filters)) = (scope, &self.state.search_filters) else {
+            return Effect::none();
+        };
+
+        self.state.searching = true;
+        self.state.search_offset = offset;
+        let token = self.state.token.clone().unwrap();
+        Effect::perform(
+            api::search_messages(token, scope, filters.to_params(offset)),
+            Message::SearchResultsLoaded,
+        )
+    }
+
+    /// Save a notification override for the selected channel.
+    fn save_channel_notifications(
+        &self,
+        channel_override: api::ChannelNotificationOverride,
+    ) -> Effect {
+        let Some(guild_id) = self.state.selected_guild.clone() else {
+            return Effect::none();
+        };
+        let token = self.state.token.clone().unwrap();
+
+        Effect::perform(
+            api::update_channel_notifications(token, guild_id, channel_override.clone()),
+            move |result| Message::ChannelNotificationsUpdated(channel_override, result),
+        )
+    }
+
+    /// Ask the gateway for the guild members (nicknames, roles) of message
+    /// authors we haven't seen yet.
+    fn request_missing_members(&self) {
+        if self.state.viewing_dms || self.state.viewing_archive() {
+            return;
+        }
+
+        let (Some(connection), Some(guild_id)) = (&self.state.gateway, &self.state.selected_guild)
+        else {
+            return;
+        };
+
+        let mut user_ids = self.state.missing_members(guild_id);
+        // The gateway accepts at most 100 ids per request
+        user_ids.truncate(100);
+        if !user_ids.is_empty() {
+            connection.send(gateway::Request::GuildMembers {
+                guild_id: guild_id.clone(),
+                user_ids,
+            });
+        }
+    }
+
+    /// Ask the gateway for the member sidebar of the selected guild channel.
+    fn subscribe_member_list(&self) {
+        if !self.state.show_member_list || self.state.viewing_dms || self.state.viewing_archive() {
+            return;
+        }
+
+        let (Some(connection), Some(guild_id), Some(channel_id)) = (
+            &self.state.gateway,
+            &self.state.selected_guild,
+            &self.state.selected_channel,
+        ) else {
+            return;
+        };
+
+        connection.send(gateway::Request::MemberList {
+            guild_id: guild_id.clone(),
+            channel_id: channel_id.clone(),
+            ranges: self.state.member_list_ranges(),
+        });
+    }
+
+    /// Fetch the icon of every DM conversation we don't have cached yet.
+    /// Group DMs are keyed by channel id, one-on-one DMs by recipient id.
+    fn load_dm_icons(&self) -> Effect {
+        let mut commands = Vec::new();
+
+        for channel in &self.state.dm_channels {
+            let icon = if channel.channel_type == 3 {
+                api::channel_icon_url(channel).map(|url| (channel.id.clone(), url))
+            } else {
+                channel
+                    .recipients
+                    .first()
+                    .and_then(|user| api::avatar_url(user).map(|url| (user.id.clone(), url)))
+            };
+
+            if let Some((key, url)) = icon {
+                if !self.state.avatars.contains_key(&key) {
+                    commands.push(Effect::perform(api::fetch_image(url), move |result| {
+                        Message::AvatarLoaded(key, result)
+                    }));
+                }
+            }
+        }
+
+        Effect::batch(commands)
+    }
+
+    /// Applies a message to the state and returns the work it starts.
+    pub fn update(&mut self, message: Message) -> Effect {
+        if self.state.offline && !message.works_offline() {
+            return Effect::none();
+        }
+
+        match message {
+            Message::TokenInputChanged(token) => {
+                self.state.token_input = token;
+                Effect::none()
+            }
+
+            Message::Login => {
+                let token = self.state.token_input.clone();
+                Effect::perform(api::verify_token(token), Message::LoginResult)
+            }
+
+            Message::LoginResult(Ok(user)) => {
+                // Whatever was browsed offline may belong to another account
+                if self.state.offline {
+                    let token_input = std::mem::take(&mut self.state.token_input);
+                    self.state = AppState::new();
+                    self.state.token_input = token_input;
+                }
+
+                // Profile saves re-verify the token too, only hydrate on login
+                let first_login = !self.state.logged_in;
+                self.state.logged_in = true;
+                self.state.current_user = Some(user.clone());
+                self.state.token = Some(self.state.token_input.clone());
+
+                if first_login {
+                    self.store = match store::Store::open(&user.id) {
+                        Ok(store) => Some(store),
+                        Err(e) => {
+                            eprintln!("Local message store unavailable: {}", e);
+                            None
+                        }
+                    };
+                    self.hydrate();
+                }
+                self.save_to_cache(|store| store.save_account(&user));
+
+                let token = self.state.token.clone().unwrap();
+                Effect::batch([
+                    Effect::perform(api::fetch_guilds(token.clone()), Message::GuildsLoaded),
+                    Effect::perform(api::fetch_mentions(token), Message::MentionsLoaded),
+                ])
+            }
+
+            Message::LoginResult(Err(e)) => {
+                self.state.error = Some(format!("Login failed: {}", e));
+                Effect::none()
+            }
+
+            Message::BrowseOffline => {
+                let Some(user) = self.state.cached_account.clone() else {
+                    return Effect::none();
+                };
+                self.state.logged_in = true;
+                self.state.offline = true;
+                self.state.current_user = Some(user);
+                self.state.search_local = true;
+                self.state.error = None;
+                self.hydrate();
+                Effect::none()
+            }
+
+            Message::ShowLogin => {
+                self.state.logged_in = false;
+                Effect::none()
+            }
+
+            Message::ArchivePathInputChanged(path) => {
+                self.state.archive_path_input = path;
+                Effect::none()
+            }
+
+            Message::OpenArchive => {
+                let path = PathBuf::from(self.state.archive_path_input.trim());
+                let archive = match export::open_archive(&path) {
+                    Ok(archive) => archive,
+                    Err(e) => {
+                        self.state.error = Some(format!("Failed to open archive: {}", e));
+                        return Effect::none();
+                    }
+                };
+
+                // Index the messages in memory so the archive can be searched
+                let messages: Vec<api::Message> =
+                    archive.messages.values().flatten().cloned().collect();
+                match store::Store::open_path(Path::new(":memory:")) {
+                    Ok(mut store) => {
+                        if let Err(e) = store.save_messages(&messages) {
+                            eprintln!("Failed to index archive: {}", e);
+                        }
+                        self.archive_stores.insert(archive.id.clone(), store);
+                    }
+                    Err(e) => eprintln!("Failed to index archive: {}", e),
+                }
+                for message in messages {
+                    self.state
+                        .user_cache
+                        .entry(message.author.id.clone())
+                        .or_insert(message.author);
+                }
+
+                // Archives need no account, without one browse them offline
+                if !self.state.logged_in {
+                    self.state.logged_in = true;
+                    self.state.offline = true;
+                    self.state.search_local = true;
+                }
+
+                let archive_id = archive.id.clone();
+                self.state.archives.retain(|a| a.id != archive_id);
+                self.state.archives.push(archive);
+                self.state.archive_path_input.clear();
+                self.state.error = None;
+                self.update(Message::SelectGuild(archive_id))
+            }
+
+            Message::GuildsLoaded(Ok(guilds)) => {
+                self.save_to_cache(|store| store.save_guilds(&guilds));
+                self.state.guilds = guilds;
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::GuildsLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load guilds: {}", e));
+                Effect::none()
+            }
+
+            Message::SelectGuild(guild_id) => self.select_guild(guild_id),
+
+            Message::ChannelsLoaded(Ok(channels)) => {
+                if let Some(guild_id) = channels.first().and_then(|c| c.guild_id.clone()) {
+                    self.save_to_cache(|store| store.save_channels(Some(&guild_id), &channels));
+                }
+                for channel in &channels {
+                    self.state
+                        .channel_cache
+                        .insert(channel.id.clone(), channel.clone());
+                }
+                self.state.channels = channels;
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::ChannelsLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load channels: {}", e));
+                Effect::none()
+            }
+
+            Message::SelectChannel(channel_id) => {
+                if !self.enter_channel(&channel_id) {
+                    return Effect::none();
+                }
+
+                let token = self.state.token.clone().unwrap();
+
+                // Forums have no messages of their own, only posts (threads)
+                let is_forum = self
+                    .state
+                    .selected_channel_info()
+                    .map(|c| c.channel_type == 15)
+                    .unwrap_or(false);
+                if is_forum {
+                    return Effect::perform(
+                        api::fetch_archived_threads(token, channel_id),
+                        Message::ArchivedThreadsLoaded,
+                    );
+                }
+
+                let loaded_id = channel_id.clone();
+                let fetch_messages = Effect::perform(
+                    api::fetch_messages(token.clone(), channel_id.clone()),
+                    move |result| Message::MessagesLoaded(loaded_id, result),
+                );
+
+                // DMs have no threads
+                if self.state.viewing_dms {
+                    return fetch_messages;
+                }
+
+                Effect::batch([
+                    fetch_messages,
+                    Effect::perform(
+                        api::fetch_archived_threads(token, channel_id),
+                        Message::ArchivedThreadsLoaded,
+                    ),
+                ])
+            }
+
+            Message::MessagesLoaded(channel_id, result)
+            | Message::MessagesAroundLoaded(channel_id, result)
+                if self.state.selected_channel.as_ref() != Some(&channel_id) =>
+            {
+                // The user moved on, but a 403 still says the channel is locked
+                if let Err(e) = result {
+                    if e.http_status() == Some(api::StatusCode::FORBIDDEN) {
+                        self.state.forbidden_channels.insert(channel_id);
+                    }
+                }
+                Effect::none()
+            }
+
+            Message::MessagesLoaded(_, Ok(messages)) => self.show_messages(messages, true),
+
+            Message::MessagesAroundLoaded(_, Ok(messages)) => self.show_messages(messages, false),
+
+            Message::MessagesLoaded(channel_id, Err(e))
+            | Message::MessagesAroundLoaded(channel_id, Err(e)) => {
+                // A 403 gets the locked empty state rather than an error
+                if e.http_status() == Some(api::StatusCode::FORBIDDEN) {
+                    self.state.forbidden_channels.insert(channel_id);
+                } else {
+                    self.state.error = Some(format!("Failed to load messages: {}", e));
+                }
+                Effect::none()
+            }
+
+            Message::BackfillGap => {
+                self.state.backfill_budget = state::BACKFILL_PAGES;
+                self.backfill()
+            }
+
+            Message::BackfillLoaded(channel_id, gap, Ok(messages)) => {
+                self.state.backfilling = false;
+                self.index_messages(&messages);
+
+                // The page ends right before the gap's newer side; a short
+                // one means nothing older is left on the server
+                let start = match messages.first() {
+                    Some(first) if messages.len() == api::MAX_MESSAGE_PAGE => first.snowflake(),
+                    _ => 0,
+                };
+                self.save_to_cache(|store| store.add_range(&channel_id, start, gap.before));
+
+                self.state.merge_messages(messages);
+                self.backfill()
+            }
+
+            Message::BackfillLoaded(_, _, Err(e)) => {
+                self.state.backfilling = false;
+                self.state.error = Some(format!("Failed to load missing messages: {}", e));
+                Effect::none()
+            }
+
+            Message::CatchUpLoaded(channel_id, after, Ok(messages)) => {
+                self.index_messages(&messages);
+                let newest = messages.last().map_or(after, api::Message::snowflake);
+                self.save_to_cache(|store| store.add_range(&channel_id, after, newest));
+
+                let more = messages.len() == api::MAX_MESSAGE_PAGE;
+                self.state.merge_messages(messages);
+                self.refresh_gaps();
+                if more {
+                    return self.catch_up_channel(channel_id, newest);
+                }
+                self.state.live_channels.insert(channel_id, newest);
+                Effect::none()
+            }
+
+            Message::CatchUpLoaded(_, _, Err(e)) => {
+                // The channel stays out of live_channels, so what arrives
+                // from now on shows up behind a gap
+                eprintln!("Failed to catch up on messages: {}", e);
+                Effect::none()
+            }
+
+            Message::MessageInputChanged(text) => {
+                self.state.message_input = text;
+                Effect::none()
+            }
+
+            Message::SendMessage => {
+                if self.state.message_input.trim().is_empty() {
+                    return Effect::none();
+                }
+
+                let token = self.state.token.clone().unwrap();
+                let channel_id = self.state.selected_channel.clone().unwrap();
+                let content = self.state.message_input.clone();
+
+                let now = Instant::now();
+                if self.state.slowmode_remaining(&channel_id, now).is_some() {
+                    return Effect::none();
+                }
+                self.state.start_slowmode(&channel_id, now);
+                self.state.message_input.clear();
+
+                Effect::perform(
+                    api::send_message(token, channel_id.clone(), content),
+                    move |result| Message::MessageSent(channel_id, result),
+                )
+            }
+
+            Message::MessageSent(channel_id, Ok(())) => {
+                // Refresh messages after sending, unless the user moved on
+                if self.state.selected_channel.as_ref() != Some(&channel_id) {
+                    return Effect::none();
+                }
+                let token = self.state.token.clone().unwrap();
+
+                Effect::perform(
+                    api::fetch_messages(token, channel_id.clone()),
+                    move |result| Message::MessagesLoaded(channel_id, result),
+                )
+            }
+
+            Message::MessageSent(channel_id, Err(e)) => {
+                // The message never landed, so no cooldown either
+                self.state.slowmode_until.remove(&channel_id);
+                self.state.error = Some(format!("Failed to send message: {}", e));
+                Effect::none()
+            }
+
+            Message::ToggleStatusMenu => {
+                self.state.status_menu_open = !self.state.status_menu_open;
+                Effect::none()
+            }
+
+            Message::ChangeStatus(new_status) => {
+                self.state.status_menu_open = false;
+                self.state.current_status = new_status;
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(api::set_status(token, new_status), Message::StatusChanged)
+            }
+
+            Message::StatusChanged(Ok(())) => {
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::StatusChanged(Err(e)) => {
+                self.state.error = Some(format!("Failed to change status: {}", e));
+                Effect::none()
+            }
+
+            Message::OpenProfileEditor => {
+                // Pre-fill inputs with current values
+                if let Some(user) = &self.state.current_user {
+                    self.state.profile_display_name_input =
+                        user.global_name.clone().unwrap_or_default();
+                    self.state.profile_bio_input = user.bio.clone().unwrap_or_default();
+                }
+                self.state.show_profile_editor = true;
+                Effect::none()
+            }
+
+            Message::CloseProfileEditor => {
+                self.state.show_profile_editor = false;
+                Effect::none()
+            }
+
+            Message::ProfileBioInputChanged(text) => {
+                self.state.profile_bio_input = text;
+                Effect::none()
+            }
+
+            Message::ProfileDisplayNameInputChanged(text) => {
+                self.state.profile_display_name_input = text;
+                Effect::none()
+            }
+
+            Message::SaveProfile => {
+                let token = self.state.token.clone().unwrap();
+                let bio = if self.state.profile_bio_input.trim().is_empty() {
+                    None
+                } else {
+                    Some(self.state.profile_bio_input.clone())
+                };
+                let display_name = if self.state.profile_display_name_input.trim().is_empty() {
+                    None
+                } else {
+                    Some(self.state.profile_display_name_input.clone())
+                };
+
+                Effect::perform(
+                    api::update_profile(token, bio, display_name),
+                    Message::ProfileSaved,
+                )
+            }
+
+            Message::ProfileSaved(Ok(())) => {
+                self.state.show_profile_editor = false;
+                self.state.error = None;
+
+                // Re-fetch user data to update display
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(api::verify_token(token), Message::LoginResult)
+            }
+
+            Message::ProfileSaved(Err(e)) => {
+                self.state.error = Some(format!("Failed to save profile: {}", e));
+                Effect::none()
+            }
+
+            Message::ViewUserProfile(user_id) => {
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::fetch_user_profile(token, user_id),
+                    Message::UserProfileLoaded,
+                )
+            }
+
+            Message::UserProfileLoaded(Ok(profile)) => {
+                self.state.viewing_user_profile = Some(profile);
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::UserProfileLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load user profile: {}", e));
+                Effect::none()
+            }
+
+            Message::CloseUserProfile => {
+                self.state.viewing_user_profile = None;
+                Effect::none()
+            }
+
+            Message::SelectDirectMessages => self.select_direct_messages(),
+
+            Message::DmChannelsLoaded(Ok(channels)) => {
+                self.save_to_cache(|store| store.save_channels(None, &channels));
+                self.state.dm_channels = channels;
+                self.state.error = None;
+                self.load_dm_icons()
+            }
+
+            Message::DmChannelsLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load direct messages: {}", e));
+                Effect::none()
+            }
+
+            Message::OpenDirectMessage(user_id) => {
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(api::create_dm(token, user_id), Message::DmOpened)
+            }
+
+            Message::DmOpened(Ok(channel)) => {
+                self.state.viewing_user_profile = None;
+                self.state.show_friends = false;
+                self.state.viewing_dms = true;
+                self.state.selected_guild = None;
+                self.state.selected_channel = Some(channel.id.clone());
+                self.state.messages.clear();
+
+                let channel_id = channel.id.clone();
+                self.state.upsert_dm_channel(channel);
+
+                let token = self.state.token.clone().unwrap();
+                Effect::batch([
+                    Effect::perform(
+                        api::fetch_messages(token, channel_id.clone()),
+                        move |result| Message::MessagesLoaded(channel_id, result),
+                    ),
+                    self.load_dm_icons(),
+                ])
+            }
+
+            Message::DmOpened(Err(e)) => {
+                self.state.error = Some(format!("Failed to open direct message: {}", e));
+                Effect::none()
+            }
+
+            Message::AvatarLoaded(key, Ok(bytes)) => {
+                self.state.avatars.insert(key, bytes);
+                Effect::none()
+            }
+
+            Message::AvatarLoaded(key, Err(e)) => {
+                // Avatars are cosmetic, don't surface this in the UI
+                eprintln!("Failed to load avatar for {}: {}", key, e);
+                Effect::none()
+            }
+
+            Message::ShowFriends => {
+                self.state.show_friends = true;
+                self.state.show_mentions = false;
+                self.state.viewing_dms = true;
+                self.state.selected_guild = None;
+                self.state.selected_channel = None;
+                self.state.messages.clear();
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::fetch_relationships(token),
+                    Message::RelationshipsLoaded,
+                )
+            }
+
+            Message::RelationshipsLoaded(Ok(relationships)) => {
+                self.state.relationships = relationships;
+                self.state.hide_blocked_mentions();
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::RelationshipsLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load friends: {}", e));
+                Effect::none()
+            }
+
+            Message::SelectFriendsTab(tab) => {
+                self.state.friends_tab = tab;
+                Effect::none()
+            }
+
+            Message::FriendRequestInputChanged(text) => {
+                self.state.friend_request_input = text;
+                Effect::none()
+            }
+
+            Message::SendFriendRequest => {
+                let username = self.state.friend_request_input.trim().to_string();
+                if username.is_empty() {
+                    return Effect::none();
+                }
+
+                self.state.friend_request_input.clear();
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::send_friend_request(token, username),
+                    Message::RelationshipUpdated,
+                )
+            }
+
+            Message::AcceptFriendRequest(user_id) => {
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::put_relationship(token, user_id, None),
+                    Message::RelationshipUpdated,
+                )
+            }
+
+            Message::RemoveRelationship(user_id) => {
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::delete_relationship(token, user_id),
+                    Message::RelationshipUpdated,
+                )
+            }
+
+            Message::BlockUser(user_id) => {
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::put_relationship(token, user_id, Some(api::RELATIONSHIP_BLOCKED)),
+                    Message::RelationshipUpdated,
+                )
+            }
+
+            Message::RelationshipUpdated(Ok(())) => {
+                // The gateway delivers the change too, but refetch in case it is down
+                self.state.error = None;
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::fetch_relationships(token),
+                    Message::RelationshipsLoaded,
+                )
+            }
+
+            Message::RelationshipUpdated(Err(e)) => {
+                self.state.error = Some(format!("Failed to update friends: {}", e));
+                Effect::none()
+            }
+
+            Message::ToggleMentions => {
+                self.state.show_mentions = !self.state.show_mentions;
+                if !self.state.show_mentions {
+                    return Effect::none();
+                }
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(api::fetch_mentions(token), Message::MentionsLoaded)
+            }
+
+            Message::MentionsLoaded(Ok(mentions)) => {
+                self.index_messages(&mentions);
+                self.state.mentions = mentions;
+                self.state.hide_blocked_mentions();
+                Effect::none()
+            }
+
+            Message::MentionsLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load mentions: {}", e));
+                Effect::none()
+            }
+
+            Message::JumpToMessage(channel_id, message_id) => {
+                // Archived messages are all loaded already
+                let in_archive = self
+                    .state
+                    .selected_archive()
+                    .is_some_and(|a| a.messages.contains_key(&channel_id));
+                if in_archive {
+                    return self.update(Message::SelectChannel(channel_id));
+                }
+                if self.state.offline {
+                    return Effect::none();
+                }
+
+                self.state.show_mentions = false;
+                let mut commands = Vec::new();
+
+                // Switch to the DM list or the guild first if the message
+                // lives elsewhere
+                let is_dm = self.state.dm_channels.iter().any(|c| c.id == channel_id);
+                if is_dm && !self.state.viewing_dms {
+                    commands.push(self.select_direct_messages());
+                } else if let Some(guild_id) = self.state.guild_for_channel(&channel_id) {
+                    if self.state.selected_guild.as_ref() != Some(&guild_id) {
+                        commands.push(self.select_guild(guild_id));
+                    }
+                }
+
+                if self.enter_channel(&channel_id) {
+                    let token = self.state.token.clone().unwrap();
+                    commands.push(Effect::perform(
+                        api::fetch_messages_around(token, channel_id.clone(), message_id),
+                        move |result| Message::MessagesAroundLoaded(channel_id, result),
+                    ));
+                }
+
+                Effect::batch(commands)
+            }
+
+            Message::MarkMentionRead(message_id) => {
+                self.state.mentions.retain(|m| m.id != message_id);
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::delete_mention(token, message_id),
+                    Message::MentionRemoved,
+                )
+            }
+
+            Message::MentionRemoved(Ok(())) => Effect::none(),
+
+            Message::MentionRemoved(Err(e)) => {
+                self.state.error = Some(format!("Failed to mark mention as read: {}", e));
+                Effect::none()
+            }
+
+            Message::ToggleBlockedMessages(run) => {
+                let revealed = run
+                    .iter()
+                    .any(|id| self.state.revealed_blocked.contains(id));
+                for id in run {
+                    if revealed {
+                        self.state.revealed_blocked.remove(&id);
+                    } else {
+                        self.state.revealed_blocked.insert(id);
+                    }
+                }
+                Effect::none()
+            }
+
+            Message::ThreadsLoaded(Ok(threads)) => {
+                // Replace the active threads of this guild, keep loaded archived ones
+                let guild_id = self.state.selected_guild.clone();
+                self.state
+                    .threads
+                    .retain(|t| t.guild_id != guild_id || t.is_archived());
+                for thread in threads {
+                    self.state.upsert_thread(thread);
+                }
+                Effect::none()
+            }
+
+            Message::ThreadsLoaded(Err(e)) => {
+                eprintln!("Failed to load active threads: {}", e);
+                Effect::none()
+            }
+
+            Message::ArchivedThreadsLoaded(Ok(threads)) => {
+                for thread in threads {
+                    self.state.upsert_thread(thread);
+                }
+                Effect::none()
+            }
+
+            Message::ArchivedThreadsLoaded(Err(e)) => {
+                eprintln!("Failed to load archived threads: {}", e);
+                Effect::none()
+            }
+
+            Message::OpenThread(thread_id) => {
+                self.state.open_thread = Some(thread_id.clone());
+                self.state.thread_messages.clear();
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::fetch_messages(token, thread_id),
+                    Message::ThreadMessagesLoaded,
+                )
+            }
+
+            Message::CloseThread => {
+                self.state.open_thread = None;
+                self.state.thread_messages.clear();
+                Effect::none()
+            }
+
+            Message::ThreadMessagesLoaded(Ok(messages)) => {
+                self.index_messages(&messages);
+                self.state.thread_messages = messages;
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::ThreadMessagesLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load thread: {}", e));
+                Effect::none()
+            }
+
+            Message::ThreadInputChanged(text) => {
+                self.state.thread_input = text;
+                Effect::none()
+            }
+
+            Message::SendThreadMessage => {
+                if self.state.thread_input.trim().is_empty() {
+                    return Effect::none();
+                }
+
+                let Some(thread_id) = self.state.open_thread.clone() else {
+                    return Effect::none();
+                };
+                let token = self.state.token.clone().unwrap();
+                let content = self.state.thread_input.clone();
+
+                let now = Instant::now();
+                if self.state.slowmode_remaining(&thread_id, now).is_some() {
+                    return Effect::none();
+                }
+                self.state.start_slowmode(&thread_id, now);
+                self.state.thread_input.clear();
+
+                Effect::perform(
+                    api::send_message(token, thread_id, content),
+                    Message::ThreadMessageSent,
+                )
+            }
+
+            Message::ThreadMessageSent(Ok(())) => {
+                let Some(thread_id) = self.state.open_thread.clone() else {
+                    return Effect::none();
+                };
+                let token = self.state.token.clone().unwrap();
+
+                Effect::perform(
+                    api::fetch_messages(token, thread_id),
+                    Message::ThreadMessagesLoaded,
+                )
+            }
+
+            Message::ThreadMessageSent(Err(e)) => {
+                if let Some(thread_id) = &self.state.open_thread {
+                    self.state.slowmode_until.remove(thread_id);
+                }
+                self.state.error = Some(format!("Failed to send message: {}", e));
+                Effect::none()
+            }
+
+            Message::StartThreadFromMessage(message_id) => {
+                // Pre-fill the name with the start of the message, like Discord does
+                self.state.thread_name_input = self
+                    .state
+                    .messages
+                    .iter()
+                    .find(|m| m.id == message_id)
+                    .map(|m| m.content.chars().take(40).collect())
+                    .unwrap_or_default();
+                self.state.creating_thread_from = Some(message_id);
+                Effect::none()
+            }
+
+            Message::CancelThreadCreation => {
+                self.state.creating_thread_from = None;
+                self.state.thread_name_input.clear();
+                Effect::none()
+            }
+
+            Message::ThreadNameInputChanged(text) => {
+                self.state.thread_name_input = text;
+                Effect::none()
+            }
+
+            Message::CreateThread => {
+                let name = self.state.thread_name_input.trim().to_string();
+                if name.is_empty() {
+                    return Effect::none();
+                }
+
+                let (Some(channel_id), Some(message_id)) = (
+                    self.state.selected_channel.clone(),
+                    self.state.creating_thread_from.take(),
+                ) else {
+                    return Effect::none();
+                };
+                self.state.thread_name_input.clear();
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::create_thread_from_message(token, channel_id, message_id, name),
+                    Message::ThreadCreated,
+                )
+            }
+
+            Message::ThreadCreated(Ok(thread)) => {
+                let thread_id = thread.id.clone();
+                self.state.upsert_thread(thread);
+                self.update(Message::OpenThread(thread_id))
+            }
+
+            Message::ThreadCreated(Err(e)) => {
+                self.state.error = Some(format!("Failed to create thread: {}", e));
+                Effect::none()
+            }
+
+            Message::JoinThread(thread_id) => {
+                if let Some(thread) = self.state.threads.iter_mut().find(|t| t.id == thread_id) {
+                    thread.member = Some(api::ThreadMember {
+                        id: Some(thread_id.clone()),
+                        user_id: self.state.current_user.as_ref().map(|u| u.id.clone()),
+                    });
+                }
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::join_thread(token, thread_id),
+                    Message::ThreadMembershipChanged,
+                )
+            }
+
+            Message::LeaveThread(thread_id) => {
+                if let Some(thread) = self.state.threads.iter_mut().find(|t| t.id == thread_id) {
+                    thread.member = None;
+                }
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::leave_thread(token, thread_id),
+                    Message::ThreadMembershipChanged,
+                )
+            }
+
+            Message::ThreadMembershipChanged(Ok(())) => Effect::none(),
+
+            Message::ThreadMembershipChanged(Err(e)) => {
+                self.state.error = Some(format!("Failed to update thread membership: {}", e));
+                Effect::none()
+            }
+
+            Message::PublishMessage(message_id) => {
+                let token = self.state.token.clone().unwrap();
+                let channel_id = self.state.selected_channel.clone().unwrap();
+                Effect::perform(
+                    api::crosspost_message(token, channel_id, message_id.clone()),
+                    move |result| Message::MessagePublished(message_id, result),
+                )
+            }
+
+            Message::MessagePublished(message_id, Ok(())) => {
+                if let Some(message) = self.state.messages.iter_mut().find(|m| m.id == message_id) {
+                    message.flags |= api::MESSAGE_FLAG_CROSSPOSTED;
+                }
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::MessagePublished(_, Err(e)) => {
+                self.state.error = Some(format!("Failed to publish message: {}", e));
+                Effect::none()
+            }
+
+            Message::ToggleFollowPicker => {
+                self.state.follow_picker_open = !self.state.follow_picker_open;
+                self.state.follow_target_guild = None;
+                self.state.follow_target_channels.clear();
+                Effect::none()
+            }
+
+            Message::SelectFollowGuild(guild_id) => {
+                self.state.follow_target_guild = Some(guild_id.clone());
+                self.state.follow_target_channels.clear();
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::fetch_channels(token, guild_id),
+                    Message::FollowChannelsLoaded,
+                )
+            }
+
+            Message::FollowChannelsLoaded(Ok(channels)) => {
+                // Follow webhooks can only post into text channels
+                self.state.follow_target_channels = channels
+                    .into_iter()
+                    .filter(|c| c.channel_type == 0)
+                    .collect();
+                Effect::none()
+            }
+
+            Message::FollowChannelsLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load channels: {}", e));
+                Effect::none()
+            }
+
+            Message::FollowChannel(target_channel_id) => {
+                self.state.follow_picker_open = false;
+
+                let token = self.state.token.clone().unwrap();
+                let channel_id = self.state.selected_channel.clone().unwrap();
+                Effect::perform(
+                    api::follow_channel(token, channel_id, target_channel_id),
+                    Message::ChannelFollowed,
+                )
+            }
+
+            Message::ChannelFollowed(Ok(())) => {
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::ChannelFollowed(Err(e)) => {
+                self.state.error = Some(format!("Failed to follow channel: {}", e));
+                Effect::none()
+            }
+
+            Message::SetForumSort(sort) => {
+                self.state.forum_sort = sort;
+                Effect::none()
+            }
+
+            Message::SetForumTagFilter(tag) => {
+                self.state.forum_tag_filter = tag;
+                Effect::none()
+            }
+
+            Message::ToggleNewPostForm => {
+                self.state.show_new_post = !self.state.show_new_post;
+                Effect::none()
+            }
+
+            Message::NewPostTitleChanged(text) => {
+                self.state.new_post_title = text;
+                Effect::none()
+            }
+
+            Message::NewPostContentChanged(text) => {
+                self.state.new_post_content = text;
+                Effect::none()
+            }
+
+            Message::ToggleNewPostTag(tag_id) => {
+                if self.state.new_post_tags.contains(&tag_id) {
+                    self.state.new_post_tags.retain(|t| t != &tag_id);
+                } else {
+                    self.state.new_post_tags.push(tag_id);
+                }
+                Effect::none()
+            }
+
+            Message::CreateForumPost => {
+                let title = self.state.new_post_title.trim().to_string();
+                let content = self.state.new_post_content.trim().to_string();
+                if title.is_empty() || content.is_empty() {
+                    return Effect::none();
+                }
+
+                let Some(forum_id) = self.state.selected_channel.clone() else {
+                    return Effect::none();
+                };
+                let tags = std::mem::take(&mut self.state.new_post_tags);
+                self.state.new_post_title.clear();
+                self.state.new_post_content.clear();
+                self.state.show_new_post = false;
+
+                let token = self.state.token.clone().unwrap();
+                Effect::perform(
+                    api::create_forum_post(token, forum_id, title, tags, content),
+                    Message::ForumPostCreated,
+                )
+            }
+
+            Message::ForumPostCreated(Ok(post)) => {
+                let post_id = post.id.clone();
+                self.state.upsert_thread(post);
+                self.update(Message::OpenThread(post_id))
+            }
+
+            Message::ForumPostCreated(Err(e)) => {
+                self.state.error = Some(format!("Failed to create post: {}", e));
+                Effect::none()
+            }
+
+            Message::ToggleLockedChannels => {
+                self.state.show_locked_channels = !self.state.show_locked_channels;
+                Effect::none()
+            }
+
+            Message::ToggleSearch => {
+                self.state.show_search = !self.state.show_search;
+                Effect::none()
+            }
+
+            Message::SearchInputChanged(text) => {
+                self.state.search_input = text;
+                Effect::none()
+            }
+
+            Message::ToggleSearchLocal => {
+                self.state.search_local = !self.state.search_local;
+                self.state.search_filters = None;
+                self.state.search_results.clear();
+                self.state.search_total = 0;
+                Effect::none()
+            }
+
+            Message::RunSearch => {
+                let query = search::SearchQuery::parse(&self.state.search_input);
+                if query.is_empty() {
+                    return Effect::none();
+                }
+                self.state.search_terms = query
+                    .content
+                    .split_whitespace()
+                    .map(str::to_lowercase)
+                    .collect();
+
+                match self.state.resolve_search(&query) {
+                    Ok(filters) => {
+                        self.state.search_filters = Some(filters);
+                        self.state.search_results.clear();
+                        self.state.search_total = 0;
+                        self.search_command(0)
+                    }
+                    Err(e) => {
+                        self.state.error = Some(format!("Invalid search: {}", e));
+                        Effect::none()
+                    }
+                }
+            }
+
+            Message::ChangeSearchPage(offset) => self.search_command(offset),
+
+            Message::SearchResultsLoaded(Ok(page)) => {
+                self.index_messages(&page.messages);
+                self.state.searching = false;
+                self.state.search_total = page.total_results;
+                self.state.search_results = page.messages;
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::SearchResultsLoaded(Err(e)) => {
+                self.state.searching = false;
+                self.state.error = Some(format!("Failed to search: {}", e));
+                Effect::none()
+            }
+
+            Message::ToggleHeaderPopover(popover) => {
+                self.state.header_popover = if self.state.header_popover == Some(popover) {
+                    None
+                } else {
+                    Some(popover)
+                };
+
+                // Pins are fetched fresh every time the popover opens
+                match (self.state.header_popover, &self.state.selected_channel) {
+                    (Some(state::HeaderPopover::Pins), Some(channel_id)) => {
+                        self.state.pins.clear();
+                        let token = self.state.token.clone().unwrap();
+                        Effect::perform(
+                            api::fetch_pins(token, channel_id.clone()),
+                            Message::PinsLoaded,
+                        )
+                    }
+                    // Exports cover the open thread if there is one
+                    (Some(state::HeaderPopover::Export), Some(channel_id)) => {
+                        let target = self.state.open_thread.clone().unwrap_or(channel_id.clone());
+                        if self.state.export_target.as_ref() != Some(&target) {
+                            let name = self
+                                .state
+                                .channel_by_id(&target)
+                                .map(|c| self.state.channel_display_name(c))
+                                .unwrap_or_default();
+                            self.state.export_directory = export::default_directory(&name, &target)
+                                .display()
+                                .to_string();
+                            self.state.export_target = Some(target);
+                        }
+                        Effect::none()
+                    }
+                    _ => Effect::none(),
+                }
+            }
+
+            Message::PinsLoaded(Ok(pins)) => {
+                self.index_messages(&pins);
+                self.state.pins = pins;
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::PinsLoaded(Err(e)) => {
+                self.state.error = Some(format!("Failed to load pins: {}", e));
+                Effect::none()
+            }
+
+            Message::PinMessage(message_id) => {
+                let token = self.state.token.clone().unwrap();
+                let channel_id = self.state.selected_channel.clone().unwrap();
+                Effect::perform(
+                    api::pin_message(token, channel_id, message_id.clone()),
+                    move |result| Message::PinChanged(message_id, true, result),
+                )
+            }
+
+            Message::UnpinMessage(message_id) => {
+                let token = self.state.token.clone().unwrap();
+                let channel_id = self.state.selected_channel.clone().unwrap();
+                Effect::perform(
+                    api::unpin_message(token, channel_id, message_id.clone()),
+                    move |result| Message::PinChanged(message_id, false, result),
+                )
+            }
+
+            Message::PinChanged(message_id, pinned, Ok(())) => {
+                if let Some(message) = self.state.messages.iter_mut().find(|m| m.id == message_id) {
+                    message.pinned = pinned;
+                    if pinned {
+                        self.state.pins.insert(0, message.clone());
+                    }
+                }
+                if !pinned {
+                    self.state.pins.retain(|m| m.id != message_id);
+                }
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::PinChanged(_, pinned, Err(e)) => {
+                let action = if pinned { "pin" } else { "unpin" };
+                self.state.error = Some(format!("Failed to {} message: {}", action, e));
+                Effect::none()
+            }
+
+            Message::ToggleTopic => {
+                self.state.topic_expanded = !self.state.topic_expanded;
+                Effect::none()
+            }
+
+            Message::SetChannelNotifications(level) => {
+                let Some(channel_id) = &self.state.selected_channel else {
+                    return Effect::none();
+                };
+                let mut channel_override = self.state.channel_notification(channel_id);
+                channel_override.message_notifications = level;
+                self.save_channel_notifications(channel_override)
+            }
+
+            Message::ToggleChannelMute => {
+                let Some(channel_id) = &self.state.selected_channel else {
+                    return Effect::none();
+                };
+                let mut channel_override = self.state.channel_notification(channel_id);
+                channel_override.muted = !channel_override.muted;
+                self.save_channel_notifications(channel_override)
+            }
+
+            Message::ChannelNotificationsUpdated(channel_override, Ok(())) => {
+                self.state
+                    .channel_notifications
+                    .insert(channel_override.channel_id.clone(), channel_override);
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::ChannelNotificationsUpdated(_, Err(e)) => {
+                self.state.error = Some(format!("Failed to update notifications: {}", e));
+                Effect::none()
+            }
+
+            Message::ToggleExportFormat(format) => {
+                if !self.state.export_formats.contains(&format) {
+                    self.state.export_formats.push(format);
+                } else {
+                    self.state.export_formats.retain(|f| *f != format);
+                }
+                Effect::none()
+            }
+
+            Message::ExportSinceChanged(text) => {
+                self.state.export_since = text;
+                Effect::none()
+            }
+
+            Message::ExportUntilChanged(text) => {
+                self.state.export_until = text;
+                Effect::none()
+            }
+
+            Message::ExportDirectoryChanged(text) => {
+                self.state.export_directory = text;
+                Effect::none()
+            }
+
+            Message::StartExport => {
+                if self.state.export_job.is_some() || self.state.export_formats.is_empty() {
+                    return Effect::none();
+                }
+                let Some(channel) = self
+                    .state
+                    .export_target
+                    .as_ref()
+                    .and_then(|id| self.state.channel_by_id(id))
+                    .cloned()
+                else {
+                    return Effect::none();
+                };
+
+                let (since, until) =
+                    match export::date_bounds(&self.state.export_since, &self.state.export_until) {
+                        Ok(bounds) => bounds,
+                        Err(e) => {
+                            self.state.error = Some(format!("Invalid export range: {}", e));
+                            return Effect::none();
+                        }
+                    };
+
+                let guild_name = channel.guild_id.as_ref().and_then(|id| {
+                    self.state
+                        .guilds
+                        .iter()
+                        .find(|g| &g.id == id)
+                        .map(|g| g.name.clone())
+                });
+                self.state.export_job = Some(export::ExportJob {
+                    channel_name: self.state.channel_display_name(&channel),
+                    channel,
+                    guild_name,
+                    formats: self.state.export_formats.clone(),
+                    since,
+                    until,
+                    directory: self.state.export_directory.trim().into(),
+                });
+                self.state.export_status = Some("Starting export...".to_string());
+                self.state.error = None;
+                Effect::none()
+            }
+
+            Message::ExportProgress(progress) => {
+                match progress {
+                    export::Progress::Fetched(count) => {
+                        self.state.export_status = Some(format!("Fetched {} messages...", count));
+                    }
+                    export::Progress::Downloading(index, total) => {
+                        self.state.export_status =
+                            Some(format!("Downloading attachment {} of {}...", index, total));
+                    }
+                    export::Progress::Finished(Ok((count, directory))) => {
+                        self.state.export_job = None;
+                        self.state.export_status = Some(format!(
+                            "Exported {} messages to {}",
+                            count,
+                            directory.display()
+                        ));
+                    }
+                    export::Progress::Finished(Err(e)) => {
+                        self.state.export_job = None;
+                        self.state.export_status = None;
+                        self.state.error = Some(format!("Export failed: {}", e));
+                    }
+                }
+                Effect::none()
+            }
+
+            Message::ToggleMemberList => {
+                self.state.show_member_list = !self.state.show_member_list;
+                self.state.reset_member_list();
+                self.subscribe_member_list();
+                Effect::none()
+            }
+
+            Message::MemberListScrolled(offset) => {
+                // Lazy load the next page once the user nears the bottom
+                let (member_count, _) = self.state.member_list_counts;
+                let loaded = self.state.member_list_pages * state::MEMBER_LIST_PAGE;
+                if offset > 0.9 && loaded < member_count {
+                    self.state.member_list_pages += 1;
+                    self.subscribe_member_list();
+                }
+                Effect::none()
+            }
+
+            Message::Gateway(event) => {
+                // A fresh connection has no subscriptions, so redo ours
+                let connected = matches!(event, gateway::Event::Connected(_));
+                let ready = matches!(event, gateway::Event::Ready(_));
+                match &event {
+                    gateway::Event::MessageCreate(message) => {
+                        self.index_messages(std::slice::from_ref(message));
+                        self.record_live_message(message);
+                    }
+                    gateway::Event::GuildMembersChunk(chunk) => {
+                        self.save_to_cache(|store| {
+                            store.save_members(&chunk.guild_id, &chunk.members)
+                        });
+                    }
+                    _ => {}
+                }
+                self.state.handle_gateway_event(event);
+                if ready {
+                    self.cache_ready();
+                }
+                if connected {
+                    self.subscribe_member_list();
+                    self.request_missing_members();
+                    return self.catch_up();
+                }
+                Effect::none()
+            }
+
+            Message::Tick(now) => {
+                self.state.prune_slowmode(now);
+                Effect::none()
+            }
+        }
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App::new()
+    }
+}
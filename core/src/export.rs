@@ -0,0 +1,496 @@
+//! Channel history export to JSON, HTML and text, and reading exports
+//! back as read-only archives.
+
+use crate::api::{self, ApiError, Channel, Message};
+use crate::search::{date_to_snowflake, SNOWFLAKES_PER_DAY};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// File names inside an export directory
+/// The JSON export, an [`Archive`].
+pub const ARCHIVE_FILE: &str = "messages.json";
+const HTML_FILE: &str = "transcript.html";
+const TEXT_FILE: &str = "transcript.txt";
+const ATTACHMENTS_DIR: &str = "attachments";
+
+// Tries per page when the network or Discord fails, waiting a little
+// longer each time. Rate limits are waited out without counting.
+const PAGE_ATTEMPTS: u32 = 4;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// A file written by an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    /// `messages.json`, which can be opened again as an archive.
+    Json,
+    /// `transcript.html`, styled like the client.
+    Html,
+    /// `transcript.txt`, one line per message.
+    Text,
+}
+
+impl ExportFormat {
+    /// Every format, in the order they are offered.
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Json, ExportFormat::Html, ExportFormat::Text];
+
+    /// The name shown on the format's toggle.
+    pub fn display_name(&self) -> &str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Text => "Text",
+        }
+    }
+}
+
+/// A channel, thread or DM to export, and where to.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    /// The channel whose history is fetched.
+    pub channel: Channel,
+    /// Name written into the exports, e.g. a DM's recipient.
+    pub channel_name: String,
+    /// The guild's name, `None` for DMs.
+    pub guild_name: Option<String>,
+    /// Files to write.
+    pub formats: Vec<ExportFormat>,
+    /// Snowflake lower bound from the date filter, inclusive.
+    pub since: Option<u64>,
+    /// Snowflake upper bound from the date filter, exclusive.
+    pub until: Option<u64>,
+    /// Directory the files and attachments are written to.
+    pub directory: PathBuf,
+}
+
+/// The JSON export: raw API models, messages oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    /// The guild's name, `None` for DMs.
+    pub guild_name: Option<String>,
+    /// The channel's name as exported.
+    pub channel_name: String,
+    /// The exported channel.
+    pub channel: Channel,
+    /// Its messages, oldest first.
+    pub messages: Vec<Message>,
+}
+
+/// Exports opened for browsing, shown as a read-only guild whose channels
+/// are the exported channels.
+#[derive(Debug, Clone)]
+pub struct ArchiveGuild {
+    /// `archive:` and the export's path, never a Discord id.
+    pub id: String,
+    /// Name shown in the guild list.
+    pub name: String,
+    /// The exported channels.
+    pub channels: Vec<Channel>,
+    /// Messages by channel id, oldest first.
+    pub messages: HashMap<String, Vec<Message>>,
+}
+
+/// Progress reports of [`export`], sent on its channel.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// Messages fetched so far.
+    Fetched(usize),
+    /// Downloading attachment n of total.
+    Downloading(usize, usize),
+    /// Done: the message count and the export directory, or why it failed.
+    Finished(Result<(usize, PathBuf), ApiError>),
+}
+
+/// Parses the optional `YYYY-MM-DD` filters into snowflake bounds. Both
+/// days are included.
+pub fn date_bounds(since: &str, until: &str) -> Result<(Option<u64>, Option<u64>), String> {
+    let parse = |date: &str| -> Result<Option<u64>, String> {
+        let date = date.trim();
+        if date.is_empty() {
+            return Ok(None);
+        }
+        date_to_snowflake(date)
+            .map(Some)
+            .ok_or(format!("Invalid date '{}', expected YYYY-MM-DD", date))
+    };
+
+    Ok((
+        parse(since)?,
+        parse(until)?.map(|id| id.saturating_add(SNOWFLAKES_PER_DAY)),
+    ))
+}
+
+/// `<documents>/discord-lite-exports/<name>-<id>`
+pub fn default_directory(channel_name: &str, channel_id: &str) -> PathBuf {
+    let name: String = channel_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("discord-lite-exports")
+        .join(format!("{}-{}", name, channel_id))
+}
+
+/// Opens an export directory, or a directory of them such as the default
+/// `discord-lite-exports`. Only the JSON archives are read.
+pub fn open_archive(path: &Path) -> Result<ArchiveGuild, String> {
+    let files: Vec<PathBuf> = if path.join(ARCHIVE_FILE).is_file() {
+        vec![path.join(ARCHIVE_FILE)]
+    } else {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().join(ARCHIVE_FILE))
+            .filter(|file| file.is_file())
+            .collect();
+        files.sort();
+        files
+    };
+    if files.is_empty() {
+        return Err(format!("No {} found in {}", ARCHIVE_FILE, path.display()));
+    }
+
+    let mut guild_names = Vec::new();
+    let mut channels = Vec::new();
+    let mut messages = HashMap::new();
+    for file in files {
+        let json = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let archive: Archive = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse {}: {}", file.display(), e))?;
+
+        // Detached from its guild and category so it lists as a top-level
+        // channel that needs no permissions
+        let mut channel = archive.channel;
+        channel.guild_id = None;
+        channel.parent_id = None;
+        channel.name = Some(archive.channel_name);
+
+        if let Some(name) = archive.guild_name {
+            if !guild_names.contains(&name) {
+                guild_names.push(name);
+            }
+        }
+        messages.insert(channel.id.clone(), archive.messages);
+        channels.push(channel);
+    }
+
+    // Exports of a single guild keep its name, anything else the folder's
+    let name = match guild_names.as_slice() {
+        [name] => name.clone(),
+        _ => path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string()),
+    };
+
+    Ok(ArchiveGuild {
+        id: format!("archive:{}", path.display()),
+        name,
+        channels,
+        messages,
+    })
+}
+
+/// Fetches the channel's history within the job's dates and writes every
+/// requested format to the job's directory. If fetching fails for good
+/// partway, the messages fetched so far are still written before the error
+/// is returned.
+pub async fn export(
+    token: String,
+    job: ExportJob,
+    mut report: impl FnMut(Progress),
+) -> Result<(usize, PathBuf), ApiError> {
+    let (messages, interrupted) = fetch_history(&token, &job, &mut report).await;
+    if let Some(e) = interrupted.as_ref().filter(|_| messages.is_empty()) {
+        return Err(e.clone());
+    }
+    let count = messages.len();
+
+    std::fs::create_dir_all(&job.directory).map_err(|e| {
+        ApiError::Other(format!(
+            "Failed to create {}: {}",
+            job.directory.display(),
+            e
+        ))
+    })?;
+
+    let archive = Archive {
+        guild_name: job.guild_name.clone(),
+        channel_name: job.channel_name.clone(),
+        channel: job.channel.clone(),
+        messages,
+    };
+
+    for format in &job.formats {
+        match format {
+            ExportFormat::Json => {
+                let json = serde_json::to_string_pretty(&archive)
+                    .map_err(|e| ApiError::Other(format!("Failed to serialize messages: {}", e)))?;
+                write(&job.directory.join(ARCHIVE_FILE), &json)?;
+            }
+            ExportFormat::Html => {
+                let attachments =
+                    download_attachments(&archive.messages, &job.directory, &mut report).await;
+                write(
+                    &job.directory.join(HTML_FILE),
+                    &render_html(&archive, &attachments),
+                )?;
+            }
+            ExportFormat::Text => {
+                write(&job.directory.join(TEXT_FILE), &render_text(&archive))?;
+            }
+        }
+    }
+
+    match interrupted {
+        Some(e) => Err(e),
+        None => Ok((count, job.directory)),
+    }
+}
+
+/// Walks the history backwards from `until` (or the newest message) until
+/// `since` or the start of the channel. Returns messages oldest first, with
+/// the error that cut the walk short, if any.
+async fn fetch_history(
+    token: &str,
+    job: &ExportJob,
+    report: &mut impl FnMut(Progress),
+) -> (Vec<Message>, Option<ApiError>) {
+    let mut messages: Vec<Message> = Vec::new();
+    let mut before = job.until;
+
+    loop {
+        let mut page = match fetch_page(token, &job.channel.id, before).await {
+            Ok(page) => page,
+            Err(e) => return (messages, Some(e)),
+        };
+        let requested = if before.is_some() {
+            api::MAX_MESSAGE_PAGE
+        } else {
+            api::MESSAGE_PAGE
+        };
+        let done = page.len() < requested
+            || page
+                .first()
+                .zip(job.since)
+                .is_some_and(|(oldest, since)| oldest.snowflake() < since);
+
+        before = page.first().map(Message::snowflake);
+        if let Some(since) = job.since {
+            page.retain(|m| m.snowflake() >= since);
+        }
+        page.append(&mut messages);
+        messages = page;
+        report(Progress::Fetched(messages.len()));
+
+        if done || before.is_none() {
+            return (messages, None);
+        }
+    }
+}
+
+/// One page of history before `before`, or the latest one. Waits out rate
+/// limits and retries transient failures a few times.
+async fn fetch_page(
+    token: &str,
+    channel_id: &str,
+    before: Option<u64>,
+) -> Result<Vec<Message>, ApiError> {
+    let mut attempt = 1;
+
+    loop {
+        let result = match before {
+            Some(id) => {
+                api::fetch_messages_before(
+                    token.to_string(),
+                    channel_id.to_string(),
+                    id.to_string(),
+                )
+                .await
+            }
+            None => api::fetch_messages(token.to_string(), channel_id.to_string()).await,
+        };
+
+        match result {
+            Err(ApiError::RateLimited { retry_after, .. }) => {
+                tokio::time::sleep(retry_after).await;
+            }
+            Err(e) if e.is_transient() && attempt < PAGE_ATTEMPTS => {
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Saves every attachment next to the transcript. Returns attachment id ->
+/// path relative to the export directory; failed downloads keep their URL.
+async fn download_attachments(
+    messages: &[Message],
+    directory: &Path,
+    report: &mut impl FnMut(Progress),
+) -> HashMap<String, String> {
+    let attachments: Vec<_> = messages.iter().flat_map(|m| &m.attachments).collect();
+    let mut saved = HashMap::new();
+    if attachments.is_empty() {
+        return saved;
+    }
+
+    if let Err(e) = std::fs::create_dir_all(directory.join(ATTACHMENTS_DIR)) {
+        eprintln!("Failed to create attachments directory: {}", e);
+        return saved;
+    }
+
+    for (index, attachment) in attachments.iter().enumerate() {
+        report(Progress::Downloading(index + 1, attachments.len()));
+
+        let file_name: String = attachment
+            .filename
+            .chars()
+            .map(|c| if matches!(c, '/' | '\\') { '_' } else { c })
+            .collect();
+        let relative = format!("{}/{}-{}", ATTACHMENTS_DIR, attachment.id, file_name);
+
+        match api::fetch_image(attachment.url.clone()).await {
+            Ok(bytes) => match std::fs::write(directory.join(&relative), bytes) {
+                Ok(()) => {
+                    saved.insert(attachment.id.clone(), relative);
+                }
+                Err(e) => eprintln!("Failed to save {}: {}", attachment.filename, e),
+            },
+            Err(e) => eprintln!("Failed to download {}: {}", attachment.filename, e),
+        }
+    }
+
+    saved
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), ApiError> {
+    std::fs::write(path, contents)
+        .map_err(|e| ApiError::Other(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+fn author_name(message: &Message) -> &str {
+    message
+        .author
+        .global_name
+        .as_deref()
+        .unwrap_or(&message.author.username)
+}
+
+/// `2024-05-01T12:34:56.789+00:00` -> `2024-05-01 12:34`
+fn short_timestamp(timestamp: &str) -> String {
+    timestamp
+        .chars()
+        .take(16)
+        .collect::<String>()
+        .replace('T', " ")
+}
+
+/// `[2024-05-01 12:34] name: content`, one line of a text transcript.
+pub fn text_line(message: &Message) -> String {
+    format!(
+        "[{}] {}: {}",
+        short_timestamp(&message.timestamp),
+        author_name(message),
+        message.content
+    )
+}
+
+fn archive_title(archive: &Archive) -> String {
+    match &archive.guild_name {
+        Some(guild) => format!("{} / #{}", guild, archive.channel_name),
+        None => archive.channel_name.clone(),
+    }
+}
+
+fn render_text(archive: &Archive) -> String {
+    let mut output = format!("{}\n\n", archive_title(archive));
+
+    for message in &archive.messages {
+        let _ = writeln!(output, "{}", text_line(message));
+        for attachment in &message.attachments {
+            let _ = writeln!(output, "    [attachment] {}", attachment.url);
+        }
+    }
+
+    output
+}
+
+const HTML_STYLE: &str = "
+body { background: #313338; color: #dbdee1; font-family: sans-serif; margin: 0; padding: 20px; }
+h1 { font-size: 20px; border-bottom: 1px solid #4e5058; padding-bottom: 10px; }
+.message { padding: 4px 0; }
+.author { font-weight: bold; color: #f2f3f5; }
+.timestamp { color: #949ba4; font-size: 12px; margin-left: 6px; }
+.content { white-space: pre-wrap; margin-top: 2px; }
+.attachment img { max-width: 400px; max-height: 300px; display: block; margin-top: 4px; }
+a { color: #00a8fc; }
+";
+
+fn render_html(archive: &Archive, attachments: &HashMap<String, String>) -> String {
+    let title = escape_html(&archive_title(archive));
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, HTML_STYLE, title
+    );
+
+    for message in &archive.messages {
+        let _ = write!(
+            output,
+            "<div class=\"message\"><span class=\"author\">{}</span><span class=\"timestamp\">{}</span><div class=\"content\">{}</div>",
+            escape_html(author_name(message)),
+            escape_html(&short_timestamp(&message.timestamp)),
+            escape_html(&message.content)
+        );
+
+        for attachment in &message.attachments {
+            let href = escape_html(attachments.get(&attachment.id).unwrap_or(&attachment.url));
+            let is_image = attachment
+                .content_type
+                .as_deref()
+                .is_some_and(|t| t.starts_with("image/"));
+            if is_image {
+                let _ = write!(
+                    output,
+                    "<div class=\"attachment\"><a href=\"{}\"><img src=\"{}\" alt=\"{}\"></a></div>",
+                    href,
+                    href,
+                    escape_html(&attachment.filename)
+                );
+            } else {
+                let _ = write!(
+                    output,
+                    "<div class=\"attachment\"><a href=\"{}\">{}</a></div>",
+                    href,
+                    escape_html(&attachment.filename)
+                );
+            }
+        }
+
+        output.push_str("</div>\n");
+    }
+
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,565 @@
+//! The Discord gateway (WebSocket): [`listen`] streams [`Event`]s and
+//! reconnects on its own, [`Connection`] sends [`Request`]s back.
+
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+
+use crate::api::{
+    Channel, ChannelNotificationOverride, GuildMember, Message, Relationship, Role, User,
+    VoiceState,
+};
+
+const GATEWAY_URL: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
+
+// Gateway opcodes
+const OP_DISPATCH: u64 = 0;
+const OP_HEARTBEAT: u64 = 1;
+const OP_IDENTIFY: u64 = 2;
+const OP_RECONNECT: u64 = 7;
+const OP_REQUEST_GUILD_MEMBERS: u64 = 8;
+const OP_INVALID_SESSION: u64 = 9;
+const OP_HELLO: u64 = 10;
+const OP_GUILD_SUBSCRIPTIONS: u64 = 14;
+
+// Ask for the deduplicated READY layout (`users` + `merged_members`)
+const CAPABILITY_DEDUPE_USER_OBJECTS: u64 = 1 << 4;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A guild as listed in READY.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReadyGuild {
+    /// The guild's id.
+    pub id: String,
+    /// Its channels.
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+    /// Active threads we can see.
+    #[serde(default)]
+    pub threads: Vec<Channel>,
+    /// Who is in which voice channel.
+    #[serde(default)]
+    pub voice_states: Vec<VoiceState>,
+    /// Its roles, for permission checks.
+    #[serde(default)]
+    pub roles: Vec<Role>,
+}
+
+/// The READY dispatch: everything the client starts from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Ready {
+    /// The logged in user.
+    pub user: User,
+    /// Every guild the user is in.
+    #[serde(default)]
+    pub guilds: Vec<ReadyGuild>,
+    /// One entry per guild, in the same order as `guilds`, holding our own
+    /// member.
+    #[serde(default)]
+    pub merged_members: Vec<Vec<GuildMember>>,
+    /// Direct and group DM channels.
+    #[serde(default)]
+    pub private_channels: Vec<Channel>,
+    /// Friends, blocks and pending requests.
+    #[serde(default)]
+    pub relationships: Vec<Relationship>,
+    /// Users referenced by id elsewhere in the payload.
+    #[serde(default)]
+    pub users: Vec<User>,
+    /// Notification settings of every guild.
+    #[serde(default)]
+    pub user_guild_settings: UserGuildSettings,
+}
+
+/// Notification settings of READY and USER_GUILD_SETTINGS_UPDATE.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UserGuildSettings {
+    /// One entry per guild that has settings.
+    #[serde(default)]
+    pub entries: Vec<GuildNotificationSettings>,
+}
+
+/// A guild's notification settings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildNotificationSettings {
+    /// The guild, `None` for the DM "guild".
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    /// Per-channel mute and notification level.
+    #[serde(default)]
+    pub channel_overrides: Vec<ChannelNotificationOverride>,
+}
+
+/// A user's online status and activities.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Presence {
+    /// The user, in READY_SUPPLEMENTAL and member lists.
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// PRESENCE_UPDATE carries a partial user instead of `user_id`.
+    #[serde(default)]
+    pub user: Option<PresenceUser>,
+    /// `online`, `idle`, `dnd` or `offline`.
+    #[serde(default)]
+    pub status: String,
+    /// Games, custom status and the like.
+    #[serde(default)]
+    pub activities: Vec<Activity>,
+}
+
+/// One of a presence's activities.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Activity {
+    /// 4 for a custom status.
+    #[serde(rename = "type", default)]
+    pub activity_type: i32,
+    /// The custom status text.
+    #[serde(default)]
+    pub state: Option<String>,
+}
+
+impl Presence {
+    /// Text of the user's custom status (activity type 4), if set.
+    pub fn custom_status(&self) -> Option<&str> {
+        self.activities
+            .iter()
+            .find(|a| a.activity_type == 4)
+            .and_then(|a| a.state.as_deref())
+    }
+
+    /// Whoever this presence is about, from either form.
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id
+            .as_deref()
+            .or(self.user.as_ref().map(|u| u.id.as_str()))
+    }
+}
+
+/// The partial user of PRESENCE_UPDATE.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PresenceUser {
+    /// The user's id.
+    pub id: String,
+}
+
+/// Friend presences of READY_SUPPLEMENTAL.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MergedPresences {
+    /// One presence per online friend.
+    #[serde(default)]
+    pub friends: Vec<Presence>,
+}
+
+/// The READY_SUPPLEMENTAL dispatch, sent right after READY.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReadySupplemental {
+    /// Friend presences.
+    #[serde(default)]
+    pub merged_presences: MergedPresences,
+}
+
+/// GUILD_MEMBERS_CHUNK, the answer to [`Request::GuildMembers`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildMembersChunk {
+    /// The guild the members belong to.
+    pub guild_id: String,
+    /// The requested members that exist.
+    #[serde(default)]
+    pub members: Vec<GuildMember>,
+}
+
+/// GUILD_MEMBER_UPDATE: a member's roles or nickname changed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildMemberUpdate {
+    /// The member's guild.
+    pub guild_id: String,
+    /// The member as it is now.
+    #[serde(flatten)]
+    pub member: GuildMember,
+}
+
+/// GUILD_ROLE_CREATE and GUILD_ROLE_UPDATE.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildRoleUpdate {
+    /// The role's guild.
+    pub guild_id: String,
+    /// The role as it is now.
+    pub role: Role,
+}
+
+/// GUILD_ROLE_DELETE.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildRoleDelete {
+    /// The role's guild.
+    pub guild_id: String,
+    /// The deleted role.
+    pub role_id: String,
+}
+
+/// THREAD_DELETE.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThreadDelete {
+    /// The deleted thread.
+    pub id: String,
+}
+
+/// GUILD_MEMBER_LIST_UPDATE, a change to a member sidebar.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberListUpdate {
+    /// The list's guild.
+    pub guild_id: String,
+    /// Identifies the list, channels with the same visibility share one.
+    #[serde(default)]
+    pub id: String,
+    /// Members who can see the channels.
+    #[serde(default)]
+    pub member_count: usize,
+    /// How many of them are online.
+    #[serde(default)]
+    pub online_count: usize,
+    /// Changes to apply, in order.
+    #[serde(default)]
+    pub ops: Vec<MemberListOp>,
+}
+
+/// One change to a member list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberListOp {
+    /// `SYNC`, `INSERT`, `UPDATE`, `DELETE` or `INVALIDATE`.
+    pub op: String,
+    /// Index range of `SYNC` and `INVALIDATE`.
+    #[serde(default)]
+    pub range: Option<[usize; 2]>,
+    /// Index of `INSERT`, `UPDATE` and `DELETE`.
+    #[serde(default)]
+    pub index: Option<usize>,
+    /// The rows of a `SYNC`.
+    #[serde(default)]
+    pub items: Vec<MemberListItem>,
+    /// The row of an `INSERT` or `UPDATE`.
+    #[serde(default)]
+    pub item: Option<MemberListItem>,
+}
+
+/// A row of a member list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberListItem {
+    /// A group header.
+    Group(MemberListGroup),
+    /// A member.
+    Member(Box<ListMember>),
+}
+
+/// A group header: online, offline or a hoisted role.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberListGroup {
+    /// `online`, `offline` or a hoisted role id.
+    pub id: String,
+    /// Members in the group.
+    #[serde(default)]
+    pub count: usize,
+}
+
+/// A member row, with the member's presence.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListMember {
+    /// The member.
+    #[serde(flatten)]
+    pub member: GuildMember,
+    /// Their presence, `None` when offline.
+    #[serde(default)]
+    pub presence: Option<Presence>,
+}
+
+/// RELATIONSHIP_REMOVE.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RelationshipRemove {
+    /// The other user's id.
+    pub id: String,
+}
+
+/// Requests sent from the app to the gateway.
+#[derive(Debug, Clone)]
+pub enum Request {
+    /// Subscribe to the member sidebar of a channel for the given index ranges.
+    MemberList {
+        /// The guild of the channel.
+        guild_id: String,
+        /// The channel whose sidebar to follow.
+        channel_id: String,
+        /// Index ranges of the rows to receive.
+        ranges: Vec<[usize; 2]>,
+    },
+    /// Fetch specific guild members, answered with GUILD_MEMBERS_CHUNK.
+    GuildMembers {
+        /// The members' guild.
+        guild_id: String,
+        /// The members to fetch.
+        user_ids: Vec<String>,
+    },
+}
+
+impl Request {
+    fn payload(&self) -> Value {
+        match self {
+            Request::MemberList {
+                guild_id,
+                channel_id,
+                ranges,
+            } => json!({
+                "op": OP_GUILD_SUBSCRIPTIONS,
+                "d": {
+                    "guild_id": guild_id,
+                    "activities": true,
+                    "threads": true,
+                    "channels": { channel_id.clone(): ranges },
+                }
+            }),
+            Request::GuildMembers { guild_id, user_ids } => json!({
+                "op": OP_REQUEST_GUILD_MEMBERS,
+                "d": {
+                    "guild_id": [guild_id],
+                    "user_ids": user_ids,
+                    "presences": false,
+                }
+            }),
+        }
+    }
+}
+
+/// Handle for sending requests over the live connection.
+#[derive(Debug, Clone)]
+pub struct Connection(tokio::sync::mpsc::UnboundedSender<Request>);
+
+impl Connection {
+    /// Queues a request, dropping it if the connection is gone.
+    pub fn send(&self, request: Request) {
+        if self.0.send(request).is_err() {
+            eprintln!("Gateway connection closed, dropping request");
+        }
+    }
+}
+
+/// Dispatches we handle, plus the connection coming and going.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A connection was made, requests can be sent through it.
+    Connected(Connection),
+    /// READY.
+    Ready(Box<Ready>),
+    /// MESSAGE_CREATE.
+    MessageCreate(Box<Message>),
+    /// CHANNEL_CREATE.
+    ChannelCreate(Box<Channel>),
+    /// READY_SUPPLEMENTAL.
+    ReadySupplemental(Box<ReadySupplemental>),
+    /// PRESENCE_UPDATE.
+    PresenceUpdate(Box<Presence>),
+    /// RELATIONSHIP_ADD.
+    RelationshipAdd(Box<Relationship>),
+    /// RELATIONSHIP_REMOVE.
+    RelationshipRemove(RelationshipRemove),
+    /// THREAD_CREATE and THREAD_UPDATE.
+    ThreadUpdate(Box<Channel>),
+    /// THREAD_DELETE.
+    ThreadDelete(ThreadDelete),
+    /// VOICE_STATE_UPDATE.
+    VoiceStateUpdate(Box<VoiceState>),
+    /// GUILD_MEMBER_LIST_UPDATE.
+    MemberListUpdate(Box<MemberListUpdate>),
+    /// GUILD_MEMBERS_CHUNK.
+    GuildMembersChunk(Box<GuildMembersChunk>),
+    /// GUILD_MEMBER_UPDATE.
+    GuildMemberUpdate(Box<GuildMemberUpdate>),
+    /// GUILD_ROLE_CREATE and GUILD_ROLE_UPDATE.
+    GuildRoleUpdate(Box<GuildRoleUpdate>),
+    /// GUILD_ROLE_DELETE.
+    GuildRoleDelete(GuildRoleDelete),
+    /// USER_GUILD_SETTINGS_UPDATE.
+    GuildSettingsUpdate(Box<GuildNotificationSettings>),
+    /// The socket dropped, with the reason. [`listen`] reconnects on its own.
+    Disconnected(String),
+}
+
+/// Streams gateway events into `output` forever, reconnecting after a short
+/// delay whenever the socket drops.
+pub async fn listen(token: String, mut output: mpsc::Sender<Event>) -> Infallible {
+    loop {
+        if let Err(e) = run(&token, &mut output).await {
+            eprintln!("Gateway disconnected: {}", e);
+            let _ = output.send(Event::Disconnected(e)).await;
+        }
+
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn run(token: &str, output: &mut mpsc::Sender<Event>) -> Result<(), String> {
+    let (stream, _) = connect_async(GATEWAY_URL)
+        .await
+        .map_err(|e| format!("Failed to connect to gateway: {}", e))?;
+    let (mut write, mut read) = stream.split();
+
+    // The first frame is always HELLO with the heartbeat interval
+    let heartbeat_interval = loop {
+        match read.next().await {
+            Some(Ok(WsMessage::Text(text))) => {
+                let payload: Value = serde_json::from_str(&text)
+                    .map_err(|e| format!("Failed to parse gateway payload: {}", e))?;
+                if payload["op"].as_u64() == Some(OP_HELLO) {
+                    break payload["d"]["heartbeat_interval"]
+                        .as_u64()
+                        .unwrap_or(41_250);
+                }
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(format!("Gateway error: {}", e)),
+            None => return Err("Gateway closed before HELLO".to_string()),
+        }
+    };
+
+    let identify = json!({
+        "op": OP_IDENTIFY,
+        "d": {
+            "token": token,
+            "capabilities": CAPABILITY_DEDUPE_USER_OBJECTS,
+            "properties": {
+                "os": std::env::consts::OS,
+                "browser": "Discord Lite",
+                "device": "",
+            },
+            "compress": false,
+        }
+    });
+    write
+        .send(WsMessage::Text(identify.to_string()))
+        .await
+        .map_err(|e| format!("Failed to identify: {}", e))?;
+
+    let (sender, mut requests) = tokio::sync::mpsc::unbounded_channel();
+    let _ = output.send(Event::Connected(Connection(sender))).await;
+
+    let mut heartbeat = tokio::time::interval(Duration::from_millis(heartbeat_interval));
+    // The first tick completes immediately, skip it so we wait a full interval
+    heartbeat.tick().await;
+
+    let mut sequence: Option<u64> = None;
+
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                let beat = json!({ "op": OP_HEARTBEAT, "d": sequence });
+                write
+                    .send(WsMessage::Text(beat.to_string()))
+                    .await
+                    .map_err(|e| format!("Failed to send heartbeat: {}", e))?;
+            }
+
+            Some(request) = requests.recv() => {
+                write
+                    .send(WsMessage::Text(request.payload().to_string()))
+                    .await
+                    .map_err(|e| format!("Failed to send gateway request: {}", e))?;
+            }
+
+            frame = read.next() => {
+                let text = match frame {
+                    Some(Ok(WsMessage::Text(text))) => text,
+                    Some(Ok(WsMessage::Close(frame))) => {
+                        return Err(format!("Gateway closed: {:?}", frame));
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(format!("Gateway error: {}", e)),
+                    None => return Err("Gateway stream ended".to_string()),
+                };
+
+                let payload: Value = match serde_json::from_str(&text) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        eprintln!("Failed to parse gateway payload: {}", e);
+                        continue;
+                    }
+                };
+
+                if let Some(s) = payload["s"].as_u64() {
+                    sequence = Some(s);
+                }
+
+                match payload["op"].as_u64() {
+                    Some(OP_DISPATCH) => {
+                        let event_name = payload["t"].as_str().unwrap_or_default();
+                        if let Some(event) = dispatch(event_name, payload["d"].clone()) {
+                            let _ = output.send(event).await;
+                        }
+                    }
+                    Some(OP_HEARTBEAT) => {
+                        let beat = json!({ "op": OP_HEARTBEAT, "d": sequence });
+                        write
+                            .send(WsMessage::Text(beat.to_string()))
+                            .await
+                            .map_err(|e| format!("Failed to send heartbeat: {}", e))?;
+                    }
+                    Some(OP_RECONNECT) => return Err("Gateway requested reconnect".to_string()),
+                    Some(OP_INVALID_SESSION) => return Err("Gateway session invalidated".to_string()),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn dispatch(event_name: &str, data: Value) -> Option<Event> {
+    let event = match event_name {
+        "READY" => serde_json::from_value(data).map(|ready| Event::Ready(Box::new(ready))),
+        "READY_SUPPLEMENTAL" => {
+            serde_json::from_value(data).map(|r| Event::ReadySupplemental(Box::new(r)))
+        }
+        "PRESENCE_UPDATE" => {
+            serde_json::from_value(data).map(|p| Event::PresenceUpdate(Box::new(p)))
+        }
+        "RELATIONSHIP_ADD" => {
+            serde_json::from_value(data).map(|r| Event::RelationshipAdd(Box::new(r)))
+        }
+        "RELATIONSHIP_REMOVE" => serde_json::from_value(data).map(Event::RelationshipRemove),
+        "THREAD_CREATE" | "THREAD_UPDATE" => {
+            serde_json::from_value(data).map(|t| Event::ThreadUpdate(Box::new(t)))
+        }
+        "THREAD_DELETE" => serde_json::from_value(data).map(Event::ThreadDelete),
+        "VOICE_STATE_UPDATE" => {
+            serde_json::from_value(data).map(|v| Event::VoiceStateUpdate(Box::new(v)))
+        }
+        "GUILD_MEMBER_LIST_UPDATE" => {
+            serde_json::from_value(data).map(|u| Event::MemberListUpdate(Box::new(u)))
+        }
+        "GUILD_MEMBERS_CHUNK" => {
+            serde_json::from_value(data).map(|c| Event::GuildMembersChunk(Box::new(c)))
+        }
+        "GUILD_MEMBER_UPDATE" => {
+            serde_json::from_value(data).map(|m| Event::GuildMemberUpdate(Box::new(m)))
+        }
+        "GUILD_ROLE_CREATE" | "GUILD_ROLE_UPDATE" => {
+            serde_json::from_value(data).map(|r| Event::GuildRoleUpdate(Box::new(r)))
+        }
+        "USER_GUILD_SETTINGS_UPDATE" => {
+            serde_json::from_value(data).map(|s| Event::GuildSettingsUpdate(Box::new(s)))
+        }
+        "GUILD_ROLE_DELETE" => serde_json::from_value(data).map(Event::GuildRoleDelete),
+        "CHANNEL_CREATE" => serde_json::from_value(data).map(|c| Event::ChannelCreate(Box::new(c))),
+        "MESSAGE_CREATE" => serde_json::from_value(data).map(|m| Event::MessageCreate(Box::new(m))),
+        _ => return None,
+    };
+
+    match event {
+        Ok(event) => Some(event),
+        Err(e) => {
+            eprintln!("Failed to parse {} event: {}", event_name, e);
+            None
+        }
+    }
+}
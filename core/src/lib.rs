@@ -0,0 +1,44 @@
+//! The frontend-independent half of discord-lite: Discord models and REST
+//! client, the gateway connection, the app state, the application model
+//! that updates it, and the local SQLite cache.
+//!
+//! Nothing here depends on a UI toolkit. A frontend turns input into
+//! [`app::Message`]s for [`app::App::update`], runs the futures of the
+//! returned [`app::Effect`] and feeds their messages back, and draws from
+//! [`state::AppState`]. Tools that need less can call [`api`] and
+//! [`gateway`] directly:
+//!
+//! ```no_run
+//! use discord_lite_core::{api, gateway, state::AppState};
+//! use futures::StreamExt;
+//!
+//! # async fn example(token: String) -> Result<(), String> {
+//! let mut state = AppState::new();
+//! state.current_user = Some(api::verify_token(token.clone()).await?);
+//! state.guilds = api::fetch_guilds(token.clone()).await?;
+//!
+//! // Live updates: READY, new messages, presences, member lists...
+//! let (sender, mut events) = futures::channel::mpsc::channel(100);
+//! tokio::spawn(gateway::listen(token, sender));
+//! while let Some(event) = events.next().await {
+//!     state.handle_gateway_event(event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! REST calls fail with [`api::ApiError`], which carries the HTTP status and
+//! displays as a message ready to show to the user, for example
+//! `"Failed to fetch messages: 403 Forbidden"`. Everything else returns
+//! `Result<_, String>` with such a message.
+
+#![warn(missing_docs)]
+
+pub mod api;
+pub mod app;
+pub mod export;
+pub mod gateway;
+pub mod permissions;
+pub mod search;
+pub mod state;
+pub mod store;
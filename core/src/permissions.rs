@@ -0,0 +1,255 @@
+//! Effective permission computation from roles and channel overwrites.
+
+use crate::api::{PermissionOverwrite, Role};
+
+// Permission bits
+/// Grants every permission and bypasses channel overwrites.
+pub const ADMINISTRATOR: u64 = 1 << 3;
+/// Edit and delete channels.
+pub const MANAGE_CHANNELS: u64 = 1 << 4;
+/// See a channel and read its messages.
+pub const VIEW_CHANNEL: u64 = 1 << 10;
+/// Post messages in a channel.
+pub const SEND_MESSAGES: u64 = 1 << 11;
+/// Post text-to-speech messages.
+pub const SEND_TTS_MESSAGES: u64 = 1 << 12;
+/// Delete and pin other people's messages.
+pub const MANAGE_MESSAGES: u64 = 1 << 13;
+/// Links in posted messages get embeds.
+pub const EMBED_LINKS: u64 = 1 << 14;
+/// Upload files.
+pub const ATTACH_FILES: u64 = 1 << 15;
+/// Ping @everyone and @here.
+pub const MENTION_EVERYONE: u64 = 1 << 17;
+/// Create and edit webhooks.
+pub const MANAGE_WEBHOOKS: u64 = 1 << 29;
+/// Start public threads.
+pub const CREATE_PUBLIC_THREADS: u64 = 1 << 35;
+/// Post messages in threads.
+pub const SEND_MESSAGES_IN_THREADS: u64 = 1 << 38;
+
+/// Every bit set, what owners and administrators get.
+pub const ALL: u64 = u64::MAX;
+
+// Overwrite targets
+/// [`PermissionOverwrite::overwrite_type`] of a role overwrite.
+pub const OVERWRITE_ROLE: i32 = 0;
+/// [`PermissionOverwrite::overwrite_type`] of a member overwrite.
+pub const OVERWRITE_MEMBER: i32 = 1;
+
+/// Discord sends permission sets as decimal strings.
+pub fn parse(bits: &str) -> u64 {
+    bits.parse().unwrap_or(0)
+}
+
+/// Whether `permissions` includes every bit of `flag`.
+pub fn has(permissions: u64, flag: u64) -> bool {
+    permissions & flag == flag
+}
+
+/// Guild-wide permissions from @everyone (the role sharing the guild's id)
+/// and the member's roles. Owners and administrators get everything.
+pub fn base_permissions(
+    guild_id: &str,
+    roles: &[Role],
+    member_roles: &[String],
+    is_owner: bool,
+) -> u64 {
+    if is_owner {
+        return ALL;
+    }
+
+    let permissions = roles
+        .iter()
+        .filter(|role| role.id == guild_id || member_roles.contains(&role.id))
+        .fold(0, |acc, role| acc | parse(&role.permissions));
+
+    if has(permissions, ADMINISTRATOR) {
+        ALL
+    } else {
+        permissions
+    }
+}
+
+/// Applies a channel's overwrites on top of the base permissions, in
+/// Discord's order: @everyone, then all of the member's roles together,
+/// then the member itself. Channels synced to their category carry the
+/// category's overwrites, so passing the channel's own list is enough.
+pub fn channel_permissions(
+    base: u64,
+    guild_id: &str,
+    user_id: &str,
+    member_roles: &[String],
+    overwrites: &[PermissionOverwrite],
+) -> u64 {
+    if has(base, ADMINISTRATOR) {
+        return ALL;
+    }
+
+    let mut permissions = base;
+    let apply = |permissions: u64, allow: u64, deny: u64| (permissions & !deny) | allow;
+
+    if let Some(everyone) = overwrites.iter().find(|o| o.id == guild_id) {
+        permissions = apply(permissions, parse(&everyone.allow), parse(&everyone.deny));
+    }
+
+    let (allow, deny) = overwrites
+        .iter()
+        .filter(|o| o.overwrite_type == OVERWRITE_ROLE && member_roles.contains(&o.id))
+        .fold((0, 0), |(allow, deny), o| {
+            (allow | parse(&o.allow), deny | parse(&o.deny))
+        });
+    permissions = apply(permissions, allow, deny);
+
+    if let Some(member) = overwrites
+        .iter()
+        .find(|o| o.overwrite_type == OVERWRITE_MEMBER && o.id == user_id)
+    {
+        permissions = apply(permissions, parse(&member.allow), parse(&member.deny));
+    }
+
+    // A channel we cannot see grants nothing, and without Send Messages
+    // none of the message-sending extras apply either
+    if !has(permissions, VIEW_CHANNEL) {
+        return 0;
+    }
+    if !has(permissions, SEND_MESSAGES) {
+        permissions &= !(SEND_TTS_MESSAGES | MENTION_EVERYONE | EMBED_LINKS | ATTACH_FILES);
+    }
+
+    permissions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: &str = "1";
+    const USER: &str = "100";
+    const MODS: &str = "20";
+
+    fn role(id: &str, permissions: u64) -> Role {
+        Role {
+            id: id.to_string(),
+            name: id.to_string(),
+            color: 0,
+            position: 0,
+            hoist: false,
+            permissions: permissions.to_string(),
+        }
+    }
+
+    fn overwrite(id: &str, overwrite_type: i32, allow: u64, deny: u64) -> PermissionOverwrite {
+        PermissionOverwrite {
+            id: id.to_string(),
+            overwrite_type,
+            allow: allow.to_string(),
+            deny: deny.to_string(),
+        }
+    }
+
+    fn roles() -> Vec<Role> {
+        vec![
+            role(GUILD, VIEW_CHANNEL | SEND_MESSAGES),
+            role(MODS, MANAGE_MESSAGES),
+        ]
+    }
+
+    #[test]
+    fn base_combines_everyone_and_member_roles() {
+        let member_roles = vec![MODS.to_string()];
+        let base = base_permissions(GUILD, &roles(), &member_roles, false);
+        assert_eq!(base, VIEW_CHANNEL | SEND_MESSAGES | MANAGE_MESSAGES);
+
+        // Roles the member doesn't have don't count
+        let base = base_permissions(GUILD, &roles(), &[], false);
+        assert_eq!(base, VIEW_CHANNEL | SEND_MESSAGES);
+    }
+
+    #[test]
+    fn owner_gets_everything() {
+        assert_eq!(base_permissions(GUILD, &[], &[], true), ALL);
+    }
+
+    #[test]
+    fn administrator_bypasses_overwrites() {
+        let roles = vec![role(GUILD, VIEW_CHANNEL), role(MODS, ADMINISTRATOR)];
+        let member_roles = vec![MODS.to_string()];
+        let base = base_permissions(GUILD, &roles, &member_roles, false);
+        assert_eq!(base, ALL);
+
+        let overwrites = [
+            overwrite(GUILD, OVERWRITE_ROLE, 0, VIEW_CHANNEL),
+            overwrite(USER, OVERWRITE_MEMBER, 0, VIEW_CHANNEL),
+        ];
+        let permissions = channel_permissions(base, GUILD, USER, &member_roles, &overwrites);
+        assert_eq!(permissions, ALL);
+    }
+
+    #[test]
+    fn role_allow_overrides_everyone_deny() {
+        let member_roles = vec![MODS.to_string()];
+        let base = base_permissions(GUILD, &roles(), &member_roles, false);
+        let overwrites = [
+            overwrite(GUILD, OVERWRITE_ROLE, 0, SEND_MESSAGES),
+            overwrite(MODS, OVERWRITE_ROLE, SEND_MESSAGES, 0),
+        ];
+
+        let permissions = channel_permissions(base, GUILD, USER, &member_roles, &overwrites);
+        assert!(has(permissions, SEND_MESSAGES));
+
+        // Without the role the @everyone deny stands
+        let permissions = channel_permissions(base, GUILD, USER, &[], &overwrites);
+        assert!(!has(permissions, SEND_MESSAGES));
+    }
+
+    #[test]
+    fn member_deny_overrides_role_allow() {
+        let member_roles = vec![MODS.to_string()];
+        let base = base_permissions(GUILD, &roles(), &member_roles, false);
+        let overwrites = [
+            overwrite(MODS, OVERWRITE_ROLE, SEND_MESSAGES, 0),
+            overwrite(USER, OVERWRITE_MEMBER, 0, SEND_MESSAGES),
+        ];
+
+        let permissions = channel_permissions(base, GUILD, USER, &member_roles, &overwrites);
+        assert!(has(permissions, VIEW_CHANNEL));
+        assert!(!has(permissions, SEND_MESSAGES));
+    }
+
+    #[test]
+    fn role_overwrites_merge_before_applying() {
+        let admins = "30";
+        let member_roles = vec![MODS.to_string(), admins.to_string()];
+        let base = base_permissions(GUILD, &roles(), &member_roles, false);
+        // One role denying and another allowing the same bit: allow wins
+        let overwrites = [
+            overwrite(MODS, OVERWRITE_ROLE, 0, SEND_MESSAGES),
+            overwrite(admins, OVERWRITE_ROLE, SEND_MESSAGES, 0),
+        ];
+
+        let permissions = channel_permissions(base, GUILD, USER, &member_roles, &overwrites);
+        assert!(has(permissions, SEND_MESSAGES));
+    }
+
+    #[test]
+    fn denied_view_denies_send() {
+        let roles = vec![role(GUILD, VIEW_CHANNEL | SEND_MESSAGES | ATTACH_FILES)];
+        let base = base_permissions(GUILD, &roles, &[], false);
+        let overwrites = [overwrite(GUILD, OVERWRITE_ROLE, 0, VIEW_CHANNEL)];
+
+        let permissions = channel_permissions(base, GUILD, USER, &[], &overwrites);
+        assert_eq!(permissions, 0);
+        assert!(!has(permissions, SEND_MESSAGES));
+    }
+
+    #[test]
+    fn denied_send_drops_send_extras() {
+        let roles = vec![role(GUILD, VIEW_CHANNEL | SEND_MESSAGES | ATTACH_FILES)];
+        let base = base_permissions(GUILD, &roles, &[], false);
+        let overwrites = [overwrite(GUILD, OVERWRITE_ROLE, 0, SEND_MESSAGES)];
+
+        let permissions = channel_permissions(base, GUILD, USER, &[], &overwrites);
+        assert_eq!(permissions, VIEW_CHANNEL);
+    }
+}
@@ -0,0 +1,274 @@
+//! Search query syntax (`from:`, `in:`, `has:`, dates...) and the filters
+//! it resolves to.
+
+// Discord's epoch (2015-01-01) in unix milliseconds
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+
+/// Results per page of the search endpoints.
+pub const SEARCH_PAGE_SIZE: u32 = 25;
+
+/// Snowflake increment for one day, used to make a date bound cover the
+/// whole day.
+pub const SNOWFLAKES_PER_DAY: u64 = 86_400_000 << 22;
+
+/// A query as typed, with names not yet resolved to ids: free text mixed
+/// with `from:`, `mentions:`, `in:`, `has:`, `before:`, `after:` and
+/// `pinned:` filters. Values with spaces can be quoted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// The words that aren't filters, joined by spaces.
+    pub content: String,
+    /// Usernames or display names of `from:`.
+    pub from: Vec<String>,
+    /// Usernames or display names of `mentions:`.
+    pub mentions: Vec<String>,
+    /// Channel names of `in:`, without the `#`.
+    pub in_channels: Vec<String>,
+    /// Lowercased `has:` values, e.g. `link` or `image`.
+    pub has: Vec<String>,
+    /// The `before:` date as typed.
+    pub before: Option<String>,
+    /// The `after:` date as typed.
+    pub after: Option<String>,
+    /// `pinned:true` or `pinned:false`.
+    pub pinned: Option<bool>,
+}
+
+/// A query with users and channels resolved, ready to send.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilters {
+    /// Free text to match.
+    pub content: String,
+    /// Ids of the `from:` users.
+    pub author_ids: Vec<String>,
+    /// Ids of the `mentions:` users.
+    pub mention_ids: Vec<String>,
+    /// Ids of the `in:` channels.
+    pub channel_ids: Vec<String>,
+    /// `has:` values, passed through.
+    pub has: Vec<String>,
+    /// Snowflake upper bound derived from the `before:` date.
+    pub max_id: Option<u64>,
+    /// Snowflake lower bound derived from the `after:` date.
+    pub min_id: Option<u64>,
+    /// `pinned:` filter, passed through.
+    pub pinned: Option<bool>,
+}
+
+impl SearchQuery {
+    /// Splits typed input into filters and free text. Unknown `key:value`
+    /// words stay in the text.
+    pub fn parse(input: &str) -> SearchQuery {
+        let mut query = SearchQuery::default();
+        let mut words = Vec::new();
+
+        for token in tokenize(input) {
+            let Some((key, value)) = token.split_once(':').filter(|(_, v)| !v.is_empty()) else {
+                words.push(token);
+                continue;
+            };
+
+            let value = value.to_string();
+            match key.to_lowercase().as_str() {
+                "from" => query.from.push(value),
+                "mentions" => query.mentions.push(value),
+                "in" => query
+                    .in_channels
+                    .push(value.trim_start_matches('#').to_string()),
+                "has" => query.has.push(value.to_lowercase()),
+                "before" => query.before = Some(value),
+                "after" => query.after = Some(value),
+                "pinned" => query.pinned = Some(value.eq_ignore_ascii_case("true")),
+                // Not a filter we know, e.g. a URL, so keep it as text
+                _ => words.push(token),
+            }
+        }
+
+        query.content = words.join(" ");
+        query
+    }
+
+    /// Whether nothing was typed, neither text nor filters.
+    pub fn is_empty(&self) -> bool {
+        *self == SearchQuery::default()
+    }
+}
+
+impl SearchFilters {
+    /// Query string parameters for the search endpoints.
+    pub fn to_params(&self, offset: u32) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+
+        if !self.content.is_empty() {
+            params.push(("content", self.content.clone()));
+        }
+        for id in &self.author_ids {
+            params.push(("author_id", id.clone()));
+        }
+        for id in &self.mention_ids {
+            params.push(("mentions", id.clone()));
+        }
+        for id in &self.channel_ids {
+            params.push(("channel_id", id.clone()));
+        }
+        for has in &self.has {
+            params.push(("has", has.clone()));
+        }
+        if let Some(max_id) = self.max_id {
+            params.push(("max_id", max_id.to_string()));
+        }
+        if let Some(min_id) = self.min_id {
+            params.push(("min_id", min_id.to_string()));
+        }
+        if let Some(pinned) = self.pinned {
+            params.push(("pinned", pinned.to_string()));
+        }
+        if offset > 0 {
+            params.push(("offset", offset.to_string()));
+        }
+
+        params
+    }
+}
+
+/// Splits on whitespace, keeping double-quoted runs together.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Converts a `YYYY-MM-DD` date (UTC midnight) to the first snowflake of that
+/// day. `None` for dates that don't exist or that a snowflake can't hold.
+pub fn date_to_snowflake(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    // Days since the unix epoch, from Howard Hinnant's days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era
+        .checked_mul(146_097)?
+        .checked_add(day_of_era - 719_468)?;
+
+    let unix_ms = u64::try_from(days.checked_mul(86_400_000)?).ok()?;
+    let timestamp = unix_ms.checked_sub(DISCORD_EPOCH_MS)?;
+    // The timestamp takes the top 42 bits of a snowflake
+    if timestamp >= 1 << 42 {
+        return None;
+    }
+    Some(timestamp << 22)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_splits_filters_from_text() {
+        let query = SearchQuery::parse("deploy from:alice in:#ops has:Link pinned:true");
+        assert_eq!(query.content, "deploy");
+        assert_eq!(query.from, ["alice"]);
+        assert_eq!(query.in_channels, ["ops"]);
+        assert_eq!(query.has, ["link"]);
+        assert_eq!(query.pinned, Some(true));
+    }
+
+    #[test]
+    fn quoted_values_keep_their_spaces() {
+        let query = SearchQuery::parse(r#"from:"Alice Smith" "release notes" draft"#);
+        assert_eq!(query.from, ["Alice Smith"]);
+        assert_eq!(query.content, "release notes draft");
+
+        // An unterminated quote runs to the end
+        assert_eq!(tokenize(r#"a "b c"#), ["a", "b c"]);
+        assert_eq!(tokenize("  a   b "), ["a", "b"]);
+    }
+
+    #[test]
+    fn unknown_and_empty_filters_stay_text() {
+        let query = SearchQuery::parse("see https://example.com color:red from:");
+        assert_eq!(query.content, "see https://example.com color:red from:");
+        assert!(query.from.is_empty());
+
+        assert!(SearchQuery::parse("").is_empty());
+        assert!(!SearchQuery::parse("from:bob").is_empty());
+    }
+
+    #[test]
+    fn dates_map_to_the_first_snowflake_of_the_day() {
+        assert_eq!(date_to_snowflake("2015-01-01"), Some(0));
+        assert_eq!(date_to_snowflake("2015-01-02"), Some(SNOWFLAKES_PER_DAY));
+        assert_eq!(
+            date_to_snowflake("2024-05-01"),
+            Some(1_235_017_845_964_800_000)
+        );
+
+        // Leap day, and the day after it
+        let leap = date_to_snowflake("2024-02-29").unwrap();
+        assert_eq!(
+            date_to_snowflake("2024-03-01"),
+            Some(leap + SNOWFLAKES_PER_DAY)
+        );
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        // Before Discord's epoch
+        assert_eq!(date_to_snowflake("2014-12-31"), None);
+        assert_eq!(date_to_snowflake("2024-13-01"), None);
+        assert_eq!(date_to_snowflake("2024-00-10"), None);
+        assert_eq!(date_to_snowflake("2024-01-32"), None);
+        assert_eq!(date_to_snowflake("2024-02-31"), None);
+        assert_eq!(date_to_snowflake("2023-02-29"), None);
+        assert_eq!(date_to_snowflake("2100-02-29"), None);
+        assert_eq!(date_to_snowflake("2024-04-31"), None);
+        assert_eq!(date_to_snowflake("2024-01"), None);
+        assert_eq!(date_to_snowflake("yesterday"), None);
+    }
+
+    #[test]
+    fn dates_a_snowflake_cannot_hold_are_rejected() {
+        // The 42-bit timestamp runs out in May 2154
+        assert!(date_to_snowflake("2154-01-01").is_some());
+        assert_eq!(date_to_snowflake("2154-12-31"), None);
+        assert_eq!(date_to_snowflake("9999-12-31"), None);
+
+        // Large enough to overflow the day and millisecond arithmetic
+        assert_eq!(date_to_snowflake("99999999999-01-01"), None);
+        assert_eq!(date_to_snowflake("9223372036854775807-12-31"), None);
+    }
+}
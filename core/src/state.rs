@@ -0,0 +1,1399 @@
+//! [`AppState`], everything a frontend shows, and the methods that fold
+//! gateway events and fetched pages into it. [`crate::app`] drives them.
+
+use crate::api::{
+    Channel, ChannelNotificationOverride, Guild, GuildMember, Message, Relationship, Role, User,
+    VoiceState, NOTIFY_INHERIT, RELATIONSHIP_BLOCKED, RELATIONSHIP_FRIEND,
+    RELATIONSHIP_INCOMING_REQUEST, RELATIONSHIP_OUTGOING_REQUEST,
+};
+use crate::export::{ArchiveGuild, ExportFormat, ExportJob};
+use crate::gateway::{Connection, Event, MemberListItem, MemberListUpdate};
+use crate::permissions;
+use crate::search::{date_to_snowflake, SearchFilters, SearchQuery, SNOWFLAKES_PER_DAY};
+use crate::store::{Cache, Gap};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+pub use crate::api::UserStatus;
+
+/// The member list is subscribed to in windows of this many rows.
+pub const MEMBER_LIST_PAGE: usize = 100;
+
+/// Pages fetched on their own to fill gaps in a channel's history, after
+/// which the user has to ask for more.
+pub const BACKFILL_PAGES: usize = 5;
+
+/// Tabs of the friends page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FriendsTab {
+    /// Friends that are online.
+    Online,
+    /// Every friend.
+    All,
+    /// Incoming and outgoing friend requests.
+    Pending,
+    /// Blocked users.
+    Blocked,
+}
+
+impl FriendsTab {
+    /// The tab's label.
+    pub fn display_name(&self) -> &str {
+        match self {
+            FriendsTab::Online => "Online",
+            FriendsTab::All => "All",
+            FriendsTab::Pending => "Pending",
+            FriendsTab::Blocked => "Blocked",
+        }
+    }
+}
+
+/// Popovers that open below the channel header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeaderPopover {
+    /// Pinned messages.
+    Pins,
+    /// Threads of the channel, active and archived.
+    Threads,
+    /// The channel's notification setting.
+    Notifications,
+    /// History export.
+    Export,
+}
+
+/// Orders of a forum's posts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForumSort {
+    /// Most recently active post first.
+    LatestActivity,
+    /// Newest post first.
+    CreationDate,
+}
+
+impl ForumSort {
+    /// The name shown in the sort picker.
+    pub fn display_name(&self) -> &str {
+        match self {
+            ForumSort::LatestActivity => "Latest Activity",
+            ForumSort::CreationDate => "Creation Date",
+        }
+    }
+}
+
+/// A row of the chat: a message, or a run of consecutive messages from
+/// blocked users that is shown collapsed behind a bar.
+#[derive(Debug, Clone, Copy)]
+pub enum ChatRow<'a> {
+    /// A message shown as is.
+    Message(&'a Message),
+    /// Consecutive messages from blocked users, oldest first.
+    Blocked(&'a [Message]),
+}
+
+/// The whole client state. Frontends draw from it;
+/// [`App::update`](crate::app::App::update) changes it, through the
+/// methods below such as [`AppState::handle_gateway_event`].
+pub struct AppState {
+    // Authentication
+    /// What's typed in the login field.
+    pub token_input: String,
+    /// The token of the session, `None` offline or logged out.
+    pub token: Option<String>,
+    /// Whether the main view is shown instead of the login.
+    pub logged_in: bool,
+    /// The logged in user.
+    pub current_user: Option<User>,
+    /// Read-only browsing of the local cache, with no token or gateway.
+    pub offline: bool,
+    /// Account of the cache found at startup, offered for offline browsing.
+    pub cached_account: Option<User>,
+
+    // Data
+    /// The user's guilds, in guild list order.
+    pub guilds: Vec<Guild>,
+    /// Channels of the selected guild.
+    pub channels: Vec<Channel>,
+    /// Messages of the selected channel, oldest first.
+    pub messages: Vec<Message>,
+
+    // Message history
+    /// Gaps in the selected channel's stored history, oldest first.
+    pub gaps: Vec<Gap>,
+    /// Whether a page is being fetched to fill a gap.
+    pub backfilling: bool,
+    /// Pages left to fetch on their own, see [`BACKFILL_PAGES`].
+    pub backfill_budget: usize,
+    /// Channel id -> newest message id with nothing missing up to the
+    /// present, for channels fetched on the current gateway connection.
+    pub live_channels: HashMap<String, u64>,
+
+    // Direct messages
+    /// Direct and group DM channels.
+    pub dm_channels: Vec<Channel>,
+    /// Whether the channel list shows DMs instead of a guild.
+    pub viewing_dms: bool,
+    /// Encoded image bytes, keyed by user id (DMs) or channel id (group DMs).
+    pub avatars: HashMap<String, Vec<u8>>,
+
+    // Friends
+    /// Friends, blocks and pending requests.
+    pub relationships: Vec<Relationship>,
+    /// Known statuses by user id; missing means offline.
+    pub presences: HashMap<String, UserStatus>,
+    /// Whether the friends page is open.
+    pub show_friends: bool,
+    /// The friends page tab.
+    pub friends_tab: FriendsTab,
+    /// Username typed in the add friend field.
+    pub friend_request_input: String,
+
+    // Selection
+    /// The selected guild, `None` on the DM list.
+    pub selected_guild: Option<String>,
+    /// The selected channel or DM.
+    pub selected_channel: Option<String>,
+
+    // Threads
+    /// Threads of the selected guild.
+    pub threads: Vec<Channel>,
+    /// The thread open in the side panel.
+    pub open_thread: Option<String>,
+    /// Messages of the open thread, oldest first.
+    pub thread_messages: Vec<Message>,
+    /// What's typed in the thread's composer.
+    pub thread_input: String,
+    /// Message a thread is being started from.
+    pub creating_thread_from: Option<String>,
+    /// Name typed for that thread.
+    pub thread_name_input: String,
+
+    // Forums
+    /// Order of the forum's posts.
+    pub forum_sort: ForumSort,
+    /// Forum tag the posts are filtered by.
+    pub forum_tag_filter: Option<String>,
+    /// Whether the new post form is open.
+    pub show_new_post: bool,
+    /// Title typed for the new post.
+    pub new_post_title: String,
+    /// Message typed for the new post.
+    pub new_post_content: String,
+    /// Tags picked for the new post.
+    pub new_post_tags: Vec<String>,
+
+    // Channel header
+    /// The popover open below the header.
+    pub header_popover: Option<HeaderPopover>,
+    /// Whether the whole topic is shown.
+    pub topic_expanded: bool,
+    /// Pinned messages of the selected channel.
+    pub pins: Vec<Message>,
+    /// The user's notification overrides by channel id.
+    pub channel_notifications: HashMap<String, ChannelNotificationOverride>,
+
+    // Message search
+    /// Whether the search panel is open.
+    pub show_search: bool,
+    /// What's typed in the search field.
+    pub search_input: String,
+    /// Filters of the last search, reused to page through it.
+    pub search_filters: Option<SearchFilters>,
+    /// Search the local index instead of Discord's servers.
+    pub search_local: bool,
+    /// Lowercased words of the query, highlighted in results.
+    pub search_terms: Vec<String>,
+    /// The current page of results.
+    pub search_results: Vec<Message>,
+    /// Matches in total.
+    pub search_total: u32,
+    /// Index of the first result on the page.
+    pub search_offset: u32,
+    /// Whether a search is running.
+    pub searching: bool,
+
+    // History export of the channel or thread in export_target
+    /// Channel or thread the export popover is for.
+    pub export_target: Option<String>,
+    /// Formats to write.
+    pub export_formats: Vec<ExportFormat>,
+    /// `YYYY-MM-DD` typed as the first day, may be empty.
+    pub export_since: String,
+    /// `YYYY-MM-DD` typed as the last day, may be empty.
+    pub export_until: String,
+    /// Directory typed to export into.
+    pub export_directory: String,
+    /// Set while an export runs, its subscription lives as long as this.
+    pub export_job: Option<ExportJob>,
+    /// Progress or outcome of the last export.
+    pub export_status: Option<String>,
+
+    /// Exports opened for browsing, listed after the guilds.
+    pub archives: Vec<ArchiveGuild>,
+    /// Path typed in the open export field.
+    pub archive_path_input: String,
+
+    // Announcement following
+    /// Whether the follow picker of an announcement channel is open.
+    pub follow_picker_open: bool,
+    /// Guild picked to follow into.
+    pub follow_target_guild: Option<String>,
+    /// Its text channels, the possible targets.
+    pub follow_target_channels: Vec<Channel>,
+
+    // Member list sidebar, rows are None until the gateway syncs them
+    /// Whether the member list is shown.
+    pub show_member_list: bool,
+    /// The rows of the list, by index.
+    pub member_list: Vec<Option<MemberListItem>>,
+    /// Id of the list Discord sends for the selected channel.
+    pub member_list_id: Option<String>,
+    /// Member and online counts.
+    pub member_list_counts: (usize, usize),
+    /// Windows of [`MEMBER_LIST_PAGE`] rows subscribed to.
+    pub member_list_pages: usize,
+
+    // Input
+    /// What's typed in the composer.
+    pub message_input: String,
+    /// Channel id -> when slowmode lets us send again.
+    pub slowmode_until: HashMap<String, Instant>,
+
+    /// Messages of the blocked runs the user chose to reveal.
+    pub revealed_blocked: HashSet<String>,
+
+    /// Channels without View Channel are hidden unless this is set.
+    pub show_locked_channels: bool,
+    /// Channels the API refused even though our permission math allowed them.
+    pub forbidden_channels: HashSet<String>,
+
+    // Status
+    /// The user's own status.
+    pub current_status: UserStatus,
+    /// Whether the status menu is open.
+    pub status_menu_open: bool,
+
+    // Profile
+    /// Whether the profile editor is open.
+    pub show_profile_editor: bool,
+    /// Bio typed in the profile editor.
+    pub profile_bio_input: String,
+    /// Display name typed in the profile editor.
+    pub profile_display_name_input: String,
+    /// Profile shown in the profile popup.
+    pub viewing_user_profile: Option<crate::api::UserProfile>,
+
+    // Mentions inbox
+    /// Messages that mention the user, newest first.
+    pub mentions: Vec<Message>,
+    /// Whether the mentions inbox is open.
+    pub show_mentions: bool,
+
+    // Gateway caches
+    /// Every channel seen, by id.
+    pub channel_cache: HashMap<String, Channel>,
+    /// Every user seen, by id.
+    pub user_cache: HashMap<String, User>,
+    /// Voice states by guild id.
+    pub voice_states: HashMap<String, Vec<VoiceState>>,
+    /// Our role ids by guild id.
+    pub my_roles: HashMap<String, Vec<String>>,
+    /// Roles by guild id.
+    pub guild_roles: HashMap<String, Vec<Role>>,
+    /// Members by guild id, then user id.
+    pub guild_members: HashMap<String, HashMap<String, GuildMember>>,
+    /// The live gateway connection, for member list requests.
+    pub gateway: Option<Connection>,
+
+    // Error handling
+    /// The last error, shown to the user until something clears it.
+    pub error: Option<String>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppState {
+    /// The state before login: nothing loaded, nothing selected.
+    pub fn new() -> Self {
+        Self {
+            token_input: String::new(),
+            token: None,
+            logged_in: false,
+            current_user: None,
+            offline: false,
+            cached_account: None,
+            guilds: Vec::new(),
+            channels: Vec::new(),
+            messages: Vec::new(),
+            gaps: Vec::new(),
+            backfilling: false,
+            backfill_budget: 0,
+            live_channels: HashMap::new(),
+            dm_channels: Vec::new(),
+            viewing_dms: false,
+            avatars: HashMap::new(),
+            relationships: Vec::new(),
+            presences: HashMap::new(),
+            show_friends: false,
+            friends_tab: FriendsTab::Online,
+            friend_request_input: String::new(),
+            selected_guild: None,
+            selected_channel: None,
+            threads: Vec::new(),
+            open_thread: None,
+            thread_messages: Vec::new(),
+            thread_input: String::new(),
+            creating_thread_from: None,
+            thread_name_input: String::new(),
+            forum_sort: ForumSort::LatestActivity,
+            forum_tag_filter: None,
+            show_new_post: false,
+            new_post_title: String::new(),
+            new_post_content: String::new(),
+            new_post_tags: Vec::new(),
+            header_popover: None,
+            topic_expanded: false,
+            pins: Vec::new(),
+            channel_notifications: HashMap::new(),
+            show_search: false,
+            search_input: String::new(),
+            search_filters: None,
+            search_local: false,
+            search_terms: Vec::new(),
+            search_results: Vec::new(),
+            search_total: 0,
+            search_offset: 0,
+            searching: false,
+            export_target: None,
+            export_formats: ExportFormat::ALL.to_vec(),
+            export_since: String::new(),
+            export_until: String::new(),
+            export_directory: String::new(),
+            export_job: None,
+            export_status: None,
+            archives: Vec::new(),
+            archive_path_input: String::new(),
+            follow_picker_open: false,
+            follow_target_guild: None,
+            follow_target_channels: Vec::new(),
+            show_member_list: false,
+            member_list: Vec::new(),
+            member_list_id: None,
+            member_list_counts: (0, 0),
+            member_list_pages: 1,
+            message_input: String::new(),
+            slowmode_until: HashMap::new(),
+            revealed_blocked: HashSet::new(),
+            show_locked_channels: false,
+            forbidden_channels: HashSet::new(),
+            current_status: UserStatus::Online,
+            status_menu_open: false,
+            show_profile_editor: false,
+            profile_bio_input: String::new(),
+            profile_display_name_input: String::new(),
+            viewing_user_profile: None,
+            mentions: Vec::new(),
+            show_mentions: false,
+            channel_cache: HashMap::new(),
+            user_cache: HashMap::new(),
+            voice_states: HashMap::new(),
+            my_roles: HashMap::new(),
+            guild_roles: HashMap::new(),
+            guild_members: HashMap::new(),
+            gateway: None,
+            error: None,
+        }
+    }
+
+    /// Adds fetched messages of the selected channel to those shown,
+    /// skipping ones already there and keeping creation order.
+    pub fn merge_messages(&mut self, messages: Vec<Message>) {
+        for message in messages {
+            if self.selected_channel.as_ref() == Some(&message.channel_id)
+                && !self.messages.iter().any(|m| m.id == message.id)
+            {
+                self.messages.push(message);
+            }
+        }
+        self.messages.sort_by_key(Message::snowflake);
+    }
+
+    /// The selected channel's messages, with runs of blocked ones grouped.
+    pub fn chat_rows(&self) -> Vec<ChatRow<'_>> {
+        let mut rows = Vec::new();
+        let mut index = 0;
+        while index < self.messages.len() {
+            let run = self.messages[index..]
+                .iter()
+                .take_while(|m| self.is_blocked(&m.author.id))
+                .count();
+            if run == 0 {
+                rows.push(ChatRow::Message(&self.messages[index]));
+                index += 1;
+            } else {
+                rows.push(ChatRow::Blocked(&self.messages[index..index + run]));
+                index += run;
+            }
+        }
+        rows
+    }
+
+    /// Whether the user chose to show a blocked run. Any of its messages
+    /// counts, so the run stays open as older or newer ones join it.
+    pub fn is_revealed(&self, run: &[Message]) -> bool {
+        run.iter().any(|m| self.revealed_blocked.contains(&m.id))
+    }
+
+    /// The newest gap that borders or falls among the shown messages.
+    pub fn visible_gap(&self) -> Option<Gap> {
+        let oldest_shown = self.messages.first()?.snowflake();
+        let newest_shown = self.messages.last()?.snowflake();
+        self.gaps
+            .iter()
+            .rev()
+            .find(|gap| gap.before >= oldest_shown && gap.after < newest_shown)
+            .copied()
+    }
+
+    /// Fills the lists from the local cache so they show before the server
+    /// answers. Whatever the server sends later replaces them.
+    pub fn hydrate(&mut self, cache: Cache) {
+        self.guilds = cache.guilds;
+        self.dm_channels = cache.dm_channels;
+        for channel in cache.channels {
+            self.channel_cache.insert(channel.id.clone(), channel);
+        }
+        for user in cache.users {
+            self.user_cache.insert(user.id.clone(), user);
+        }
+
+        // Our own member entries give the roles channel overwrites apply to
+        if let Some(user_id) = self.current_user.as_ref().map(|u| u.id.clone()) {
+            for (guild_id, members) in &cache.members {
+                if let Some(member) = members.get(&user_id) {
+                    self.my_roles.insert(guild_id.clone(), member.roles.clone());
+                }
+            }
+        }
+        self.guild_members = cache.members;
+    }
+
+    /// Applies a gateway event: new messages, presences, member lists,
+    /// voice states, roles and the like.
+    pub fn handle_gateway_event(&mut self, event: Event) {
+        match event {
+            Event::Connected(connection) => {
+                self.gateway = Some(connection);
+            }
+
+            Event::GuildMembersChunk(chunk) => {
+                for member in chunk.members {
+                    self.cache_member(&chunk.guild_id, member);
+                }
+            }
+
+            Event::GuildMemberUpdate(update) => {
+                self.cache_member(&update.guild_id, update.member);
+            }
+
+            Event::GuildRoleUpdate(update) => {
+                let roles = self.guild_roles.entry(update.guild_id).or_default();
+                roles.retain(|r| r.id != update.role.id);
+                roles.push(update.role);
+            }
+
+            Event::GuildRoleDelete(deleted) => {
+                if let Some(roles) = self.guild_roles.get_mut(&deleted.guild_id) {
+                    roles.retain(|r| r.id != deleted.role_id);
+                }
+            }
+
+            Event::MemberListUpdate(update) => {
+                if self.selected_guild.as_ref() == Some(&update.guild_id) {
+                    self.apply_member_list_update(*update);
+                }
+            }
+
+            Event::Ready(ready) => {
+                for (index, guild) in ready.guilds.into_iter().enumerate() {
+                    // Our own member object sits at the same index in merged_members
+                    if let Some(member) = ready
+                        .merged_members
+                        .get(index)
+                        .and_then(|members| members.first())
+                    {
+                        self.my_roles.insert(guild.id.clone(), member.roles.clone());
+                    }
+
+                    for member in ready.merged_members.get(index).into_iter().flatten() {
+                        self.cache_member(&guild.id, member.clone());
+                    }
+
+                    self.guild_roles.insert(guild.id.clone(), guild.roles);
+
+                    for mut channel in guild.channels {
+                        channel.guild_id = Some(guild.id.clone());
+                        self.channel_cache.insert(channel.id.clone(), channel);
+                    }
+
+                    for mut voice_state in guild.voice_states {
+                        voice_state.guild_id = Some(guild.id.clone());
+                        self.update_voice_state(voice_state);
+                    }
+
+                    for mut thread in guild.threads {
+                        thread.guild_id = Some(guild.id.clone());
+                        self.upsert_thread(thread);
+                    }
+                }
+
+                for user in &ready.users {
+                    self.user_cache.insert(user.id.clone(), user.clone());
+                }
+
+                let mut dm_channels = ready.private_channels;
+                for channel in &mut dm_channels {
+                    if channel.recipients.is_empty() {
+                        channel.recipients = ready
+                            .users
+                            .iter()
+                            .filter(|u| channel.recipient_ids.contains(&u.id))
+                            .cloned()
+                            .collect();
+                    }
+                }
+                self.dm_channels = dm_channels;
+
+                let mut relationships = ready.relationships;
+                for relationship in &mut relationships {
+                    if relationship.user.is_none() {
+                        relationship.user = ready
+                            .users
+                            .iter()
+                            .find(|u| u.id == relationship.id)
+                            .cloned();
+                    }
+                }
+                self.relationships = relationships;
+
+                for entry in ready.user_guild_settings.entries {
+                    self.apply_notification_settings(entry.channel_overrides);
+                }
+            }
+
+            Event::GuildSettingsUpdate(settings) => {
+                self.apply_notification_settings(settings.channel_overrides);
+            }
+
+            Event::ReadySupplemental(supplemental) => {
+                for presence in &supplemental.merged_presences.friends {
+                    if let Some(user_id) = presence.user_id() {
+                        self.set_presence(user_id, &presence.status);
+                    }
+                }
+            }
+
+            Event::PresenceUpdate(presence) => {
+                if let Some(user_id) = presence.user_id() {
+                    self.set_presence(user_id, &presence.status);
+                }
+            }
+
+            Event::RelationshipAdd(relationship) => {
+                let blocked = relationship.relationship_type == RELATIONSHIP_BLOCKED;
+                self.relationships.retain(|r| r.id != relationship.id);
+                self.relationships.push(*relationship);
+                if blocked {
+                    self.hide_blocked_mentions();
+                }
+            }
+
+            Event::RelationshipRemove(removed) => {
+                self.relationships.retain(|r| r.id != removed.id);
+            }
+
+            Event::ThreadUpdate(thread) => {
+                if thread.is_thread() {
+                    self.upsert_thread(*thread);
+                }
+            }
+
+            Event::ThreadDelete(deleted) => {
+                self.threads.retain(|t| t.id != deleted.id);
+                if self.open_thread.as_ref() == Some(&deleted.id) {
+                    self.open_thread = None;
+                    self.thread_messages.clear();
+                }
+            }
+
+            Event::VoiceStateUpdate(voice_state) => {
+                self.update_voice_state(*voice_state);
+            }
+
+            Event::ChannelCreate(channel) => {
+                if channel.channel_type == 1 || channel.channel_type == 3 {
+                    self.upsert_dm_channel(*channel);
+                }
+            }
+
+            Event::MessageCreate(message) => {
+                self.user_cache
+                    .insert(message.author.id.clone(), message.author.clone());
+
+                if let (Some(guild_id), Some(member)) = (&message.guild_id, &message.member) {
+                    let mut member = member.clone();
+                    member.user = Some(message.author.clone());
+                    self.cache_member(guild_id, member);
+                }
+
+                if self.mentions_me(&message) && !self.mentions.iter().any(|m| m.id == message.id) {
+                    self.mentions.insert(0, (*message).clone());
+                }
+
+                if let Some(dm) = self
+                    .dm_channels
+                    .iter_mut()
+                    .find(|c| c.id == message.channel_id)
+                {
+                    dm.last_message_id = Some(message.id.clone());
+                }
+
+                if let Some(thread) = self.threads.iter_mut().find(|t| t.id == message.channel_id) {
+                    thread.message_count = Some(thread.message_count.unwrap_or(0) + 1);
+                }
+
+                if self.open_thread.as_deref() == Some(message.channel_id.as_str())
+                    && !self.thread_messages.iter().any(|m| m.id == message.id)
+                {
+                    self.thread_messages.push((*message).clone());
+                }
+
+                if self.selected_channel.as_deref() == Some(message.channel_id.as_str())
+                    && !self.messages.iter().any(|m| m.id == message.id)
+                {
+                    self.messages.push(*message);
+                }
+            }
+
+            Event::Disconnected(_) => {
+                self.gateway = None;
+            }
+        }
+    }
+
+    fn apply_member_list_update(&mut self, update: MemberListUpdate) {
+        // A different list id means the channel's visibility changed the list entirely
+        if self.member_list_id.as_ref() != Some(&update.id) {
+            self.member_list.clear();
+            self.member_list_id = Some(update.id.clone());
+        }
+        self.member_list_counts = (update.member_count, update.online_count);
+
+        for op in update.ops {
+            let items: Vec<MemberListItem> = op.item.into_iter().chain(op.items).collect();
+            for item in &items {
+                if let MemberListItem::Member(member) = item {
+                    if let (Some(user), Some(presence)) = (&member.member.user, &member.presence) {
+                        self.set_presence(&user.id, &presence.status);
+                    }
+                    self.cache_member(&update.guild_id, member.member.clone());
+                }
+            }
+
+            match op.op.as_str() {
+                "SYNC" => {
+                    let Some([start, _]) = op.range else { continue };
+                    for (offset, item) in items.into_iter().enumerate() {
+                        let index = start + offset;
+                        if self.member_list.len() <= index {
+                            self.member_list.resize(index + 1, None);
+                        }
+                        self.member_list[index] = Some(item);
+                    }
+                }
+                "INSERT" => {
+                    let (Some(index), Some(item)) = (op.index, items.into_iter().last()) else {
+                        continue;
+                    };
+                    if index <= self.member_list.len() {
+                        self.member_list.insert(index, Some(item));
+                    }
+                }
+                "UPDATE" => {
+                    let (Some(index), Some(item)) = (op.index, items.into_iter().last()) else {
+                        continue;
+                    };
+                    if let Some(slot) = self.member_list.get_mut(index) {
+                        *slot = Some(item);
+                    }
+                }
+                "DELETE" => {
+                    if let Some(index) = op.index.filter(|i| *i < self.member_list.len()) {
+                        self.member_list.remove(index);
+                    }
+                }
+                "INVALIDATE" => {
+                    let Some([start, end]) = op.range else {
+                        continue;
+                    };
+                    for slot in self.member_list.iter_mut().take(end + 1).skip(start) {
+                        *slot = None;
+                    }
+                }
+                other => eprintln!("Unknown member list op: {}", other),
+            }
+        }
+    }
+
+    fn cache_member(&mut self, guild_id: &str, mut member: GuildMember) {
+        let Some(user_id) = member.id().map(str::to_string) else {
+            return;
+        };
+        if let Some(user) = &member.user {
+            self.user_cache.insert(user_id.clone(), user.clone());
+        }
+
+        let members = self.guild_members.entry(guild_id.to_string()).or_default();
+        // Partial members (e.g. from READY) carry no user, keep the one we know
+        if member.user.is_none() {
+            member.user = members.get(&user_id).and_then(|m| m.user.clone());
+        }
+        members.insert(user_id, member);
+    }
+
+    /// Authors of the loaded messages whose guild member we haven't seen yet.
+    pub fn missing_members(&self, guild_id: &str) -> Vec<String> {
+        let known = self.guild_members.get(guild_id);
+        let mut missing: Vec<String> = Vec::new();
+        for message in &self.messages {
+            let id = &message.author.id;
+            if !known.is_some_and(|m| m.contains_key(id)) && !missing.contains(id) {
+                missing.push(id.clone());
+            }
+        }
+        missing
+    }
+
+    /// Name and role color to show for a message author: nick, then global
+    /// name, then username.
+    pub fn author_display(&self, message: &Message) -> (String, Option<[f32; 3]>) {
+        let author = &message.author;
+        let fallback = author
+            .global_name
+            .clone()
+            .unwrap_or_else(|| author.username.clone());
+
+        let Some(guild_id) = message
+            .guild_id
+            .clone()
+            .or_else(|| self.guild_for_channel(&message.channel_id))
+        else {
+            return (fallback, None);
+        };
+
+        let member = self
+            .guild_members
+            .get(&guild_id)
+            .and_then(|members| members.get(&author.id))
+            .or(message.member.as_ref());
+
+        match member {
+            Some(member) => (
+                member.nick.clone().unwrap_or(fallback),
+                self.member_color(&guild_id, &member.roles),
+            ),
+            None => (fallback, None),
+        }
+    }
+
+    /// Forgets the member list, e.g. when another channel is selected.
+    pub fn reset_member_list(&mut self) {
+        self.member_list.clear();
+        self.member_list_id = None;
+        self.member_list_counts = (0, 0);
+        self.member_list_pages = 1;
+    }
+
+    /// Index ranges to subscribe to: the first page plus the two deepest loaded,
+    /// since Discord only tracks three ranges per channel.
+    pub fn member_list_ranges(&self) -> Vec<[usize; 2]> {
+        let last = self.member_list_pages.saturating_sub(1);
+        let mut pages = vec![0];
+        if last >= 2 {
+            pages.push(last - 1);
+        }
+        if last >= 1 {
+            pages.push(last);
+        }
+
+        pages
+            .into_iter()
+            .map(|page| {
+                let start = page * MEMBER_LIST_PAGE;
+                [start, start + MEMBER_LIST_PAGE - 1]
+            })
+            .collect()
+    }
+
+    /// Color of a member's highest colored role.
+    pub fn member_color(&self, guild_id: &str, role_ids: &[String]) -> Option<[f32; 3]> {
+        self.guild_roles
+            .get(guild_id)?
+            .iter()
+            .filter(|role| role_ids.contains(&role.id))
+            .filter_map(|role| role.rgb().map(|rgb| (role.position, rgb)))
+            .max_by_key(|(position, _)| *position)
+            .map(|(_, rgb)| rgb)
+    }
+
+    /// Heading of a member list group: Online, Offline or the hoisted role's
+    /// name.
+    pub fn member_list_group_name(&self, group_id: &str) -> String {
+        match group_id {
+            "online" => "Online".to_string(),
+            "offline" => "Offline".to_string(),
+            role_id => self
+                .selected_guild
+                .as_ref()
+                .and_then(|guild_id| self.guild_roles.get(guild_id))
+                .and_then(|roles| roles.iter().find(|r| r.id == role_id))
+                .map(|r| r.name.clone())
+                .unwrap_or_else(|| "Unknown role".to_string()),
+        }
+    }
+
+    /// Whether the user has blocked `user_id`.
+    pub fn is_blocked(&self, user_id: &str) -> bool {
+        self.relationships
+            .iter()
+            .any(|r| r.id == user_id && r.relationship_type == RELATIONSHIP_BLOCKED)
+    }
+
+    /// Drops pings from blocked users out of the mentions inbox.
+    pub fn hide_blocked_mentions(&mut self) {
+        let blocked: HashSet<&str> = self
+            .relationships
+            .iter()
+            .filter(|r| r.relationship_type == RELATIONSHIP_BLOCKED)
+            .map(|r| r.id.as_str())
+            .collect();
+        self.mentions
+            .retain(|m| !blocked.contains(m.author.id.as_str()));
+    }
+
+    fn apply_notification_settings(&mut self, overrides: Vec<ChannelNotificationOverride>) {
+        for channel_override in overrides {
+            self.channel_notifications
+                .insert(channel_override.channel_id.clone(), channel_override);
+        }
+    }
+
+    /// Our notification override for a channel, inheriting when unset.
+    pub fn channel_notification(&self, channel_id: &str) -> ChannelNotificationOverride {
+        self.channel_notifications
+            .get(channel_id)
+            .cloned()
+            .unwrap_or_else(|| ChannelNotificationOverride {
+                channel_id: channel_id.to_string(),
+                message_notifications: NOTIFY_INHERIT,
+                muted: false,
+            })
+    }
+
+    /// Forgets cooldowns that ended before `now`.
+    pub fn prune_slowmode(&mut self, now: Instant) {
+        self.slowmode_until.retain(|_, until| *until > now);
+    }
+
+    /// Starts the slowmode cooldown after a send. Manage Messages or Manage
+    /// Channels bypass it.
+    pub fn start_slowmode(&mut self, channel_id: &str, now: Instant) {
+        let Some(channel) = self.channel_by_id(channel_id) else {
+            return;
+        };
+        if channel.rate_limit_per_user == 0
+            || self.can(channel, permissions::MANAGE_MESSAGES)
+            || self.can(channel, permissions::MANAGE_CHANNELS)
+        {
+            return;
+        }
+
+        let until = now + Duration::from_secs(channel.rate_limit_per_user.into());
+        self.slowmode_until.insert(channel_id.to_string(), until);
+    }
+
+    /// Time left before slowmode lets the user send in the channel again.
+    pub fn slowmode_remaining(&self, channel_id: &str, now: Instant) -> Option<Duration> {
+        self.slowmode_until
+            .get(channel_id)
+            .filter(|until| **until > now)
+            .map(|until| *until - now)
+    }
+
+    fn set_presence(&mut self, user_id: &str, status: &str) {
+        match UserStatus::from_api(status) {
+            Some(status) => {
+                self.presences.insert(user_id.to_string(), status);
+            }
+            None => {
+                self.presences.remove(user_id);
+            }
+        }
+    }
+
+    /// Relationships shown under the given Friends tab.
+    pub fn relationships_for_tab(&self, tab: FriendsTab) -> Vec<&Relationship> {
+        self.relationships
+            .iter()
+            .filter(|r| match tab {
+                FriendsTab::Online => {
+                    r.relationship_type == RELATIONSHIP_FRIEND
+                        && matches!(
+                            self.presences.get(&r.id),
+                            Some(UserStatus::Online | UserStatus::Idle | UserStatus::DoNotDisturb)
+                        )
+                }
+                FriendsTab::All => r.relationship_type == RELATIONSHIP_FRIEND,
+                FriendsTab::Pending => {
+                    r.relationship_type == RELATIONSHIP_INCOMING_REQUEST
+                        || r.relationship_type == RELATIONSHIP_OUTGOING_REQUEST
+                }
+                FriendsTab::Blocked => r.relationship_type == RELATIONSHIP_BLOCKED,
+            })
+            .collect()
+    }
+
+    /// Whether a message pings the current user directly or through one of their roles.
+    pub fn mentions_me(&self, message: &Message) -> bool {
+        let Some(user) = &self.current_user else {
+            return false;
+        };
+
+        if message.author.id == user.id || self.is_blocked(&message.author.id) {
+            return false;
+        }
+
+        if message.mentions.iter().any(|u| u.id == user.id) {
+            return true;
+        }
+
+        let guild_id = message
+            .guild_id
+            .clone()
+            .or_else(|| self.guild_for_channel(&message.channel_id));
+
+        guild_id
+            .and_then(|id| self.my_roles.get(&id))
+            .map(|roles| message.mention_roles.iter().any(|r| roles.contains(r)))
+            .unwrap_or(false)
+    }
+
+    /// Adds a DM channel, or replaces it if already known.
+    pub fn upsert_dm_channel(&mut self, channel: Channel) {
+        match self.dm_channels.iter_mut().find(|c| c.id == channel.id) {
+            Some(existing) => *existing = channel,
+            None => self.dm_channels.push(channel),
+        }
+    }
+
+    /// Track who is connected where, dropping users that left voice.
+    pub fn update_voice_state(&mut self, voice_state: VoiceState) {
+        let Some(guild_id) = voice_state.guild_id.clone() else {
+            return;
+        };
+
+        if let Some(user) = voice_state.member.as_ref().and_then(|m| m.user.as_ref()) {
+            self.user_cache.insert(user.id.clone(), user.clone());
+        }
+
+        let states = self.voice_states.entry(guild_id).or_default();
+        states.retain(|v| v.user_id != voice_state.user_id);
+        if voice_state.channel_id.is_some() {
+            states.push(voice_state);
+        }
+    }
+
+    /// Users connected to a voice or stage channel.
+    pub fn voice_occupants(&self, channel: &Channel) -> Vec<&VoiceState> {
+        channel
+            .guild_id
+            .as_ref()
+            .and_then(|id| self.voice_states.get(id))
+            .map(|states| {
+                states
+                    .iter()
+                    .filter(|v| v.channel_id.as_ref() == Some(&channel.id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Best name we know for a voice participant.
+    pub fn voice_display_name(&self, voice_state: &VoiceState) -> String {
+        if let Some(nick) = voice_state.member.as_ref().and_then(|m| m.nick.clone()) {
+            return nick;
+        }
+
+        voice_state
+            .member
+            .as_ref()
+            .and_then(|m| m.user.as_ref())
+            .or_else(|| self.user_cache.get(&voice_state.user_id))
+            .map(|u| u.global_name.clone().unwrap_or(u.username.clone()))
+            .unwrap_or_else(|| voice_state.user_id.clone())
+    }
+
+    /// Insert or refresh a thread, keeping our membership if the update omits it.
+    pub fn upsert_thread(&mut self, mut thread: Channel) {
+        match self.threads.iter_mut().find(|t| t.id == thread.id) {
+            Some(existing) => {
+                if thread.member.is_none() {
+                    thread.member = existing.member.take();
+                }
+                *existing = thread;
+            }
+            None => self.threads.push(thread),
+        }
+    }
+
+    /// Threads under a channel, active ones first.
+    pub fn threads_for(&self, parent_id: &str) -> Vec<&Channel> {
+        let mut threads: Vec<_> = self
+            .threads
+            .iter()
+            .filter(|t| t.parent_id.as_deref() == Some(parent_id))
+            .collect();
+        threads.sort_by_key(|t| t.is_archived());
+        threads
+    }
+
+    /// Our base permission bits in a guild.
+    pub fn guild_permissions(&self, guild_id: &str) -> u64 {
+        let Some(guild) = self.guilds.iter().find(|g| g.id == guild_id) else {
+            return 0;
+        };
+
+        if let (Some(roles), Some(my_roles)) =
+            (self.guild_roles.get(guild_id), self.my_roles.get(guild_id))
+        {
+            return permissions::base_permissions(guild_id, roles, my_roles, guild.owner);
+        }
+
+        // Until READY arrives only the summary from the guild list is known
+        let summary = guild
+            .permissions
+            .as_deref()
+            .map(permissions::parse)
+            .unwrap_or(0);
+        if guild.owner || permissions::has(summary, permissions::ADMINISTRATOR) {
+            permissions::ALL
+        } else {
+            summary
+        }
+    }
+
+    /// Effective permissions in a channel. Threads use their parent's
+    /// overwrites, DMs allow everything.
+    pub fn channel_permissions(&self, channel: &Channel) -> u64 {
+        let Some(guild_id) = channel.guild_id.as_deref() else {
+            return permissions::ALL;
+        };
+
+        let source = if channel.is_thread() {
+            channel
+                .parent_id
+                .as_ref()
+                .and_then(|id| {
+                    self.channels
+                        .iter()
+                        .find(|c| &c.id == id)
+                        .or_else(|| self.channel_cache.get(id))
+                })
+                .unwrap_or(channel)
+        } else {
+            channel
+        };
+
+        let user_id = self
+            .current_user
+            .as_ref()
+            .map(|u| u.id.as_str())
+            .unwrap_or_default();
+        let my_roles = self
+            .my_roles
+            .get(guild_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        permissions::channel_permissions(
+            self.guild_permissions(guild_id),
+            guild_id,
+            user_id,
+            my_roles,
+            &source.permission_overwrites,
+        )
+    }
+
+    /// Whether the user has `permission` in the channel.
+    pub fn can(&self, channel: &Channel, permission: u64) -> bool {
+        permissions::has(self.channel_permissions(channel), permission)
+    }
+
+    /// Whether the channel may be opened: View Channel is granted and the
+    /// API hasn't refused it.
+    pub fn can_view(&self, channel: &Channel) -> bool {
+        !self.forbidden_channels.contains(&channel.id)
+            && self.can(channel, permissions::VIEW_CHANNEL)
+    }
+
+    /// Whether the composer may send in the channel, threads having their own flag.
+    pub fn can_send_in(&self, channel: &Channel) -> bool {
+        if channel.is_thread() {
+            self.can(channel, permissions::SEND_MESSAGES_IN_THREADS) && !channel.is_locked()
+        } else {
+            self.can(channel, permissions::SEND_MESSAGES)
+        }
+    }
+
+    /// Guilds other than the current one where we may create follow webhooks.
+    pub fn follow_target_guilds(&self) -> Vec<&Guild> {
+        self.guilds
+            .iter()
+            .filter(|g| Some(&g.id) != self.selected_guild.as_ref())
+            .filter(|g| {
+                permissions::has(self.guild_permissions(&g.id), permissions::MANAGE_WEBHOOKS)
+            })
+            .collect()
+    }
+
+    /// Posts of a forum channel, filtered by the selected tag and sorted.
+    pub fn forum_posts(&self, forum_id: &str) -> Vec<&Channel> {
+        let snowflake = |id: Option<&str>| id.and_then(|id| id.parse::<u64>().ok()).unwrap_or(0);
+
+        let mut posts: Vec<_> = self
+            .threads_for(forum_id)
+            .into_iter()
+            .filter(|post| match &self.forum_tag_filter {
+                Some(tag) => post.applied_tags.contains(tag),
+                None => true,
+            })
+            .collect();
+
+        match self.forum_sort {
+            ForumSort::LatestActivity => posts.sort_by_key(|p| {
+                std::cmp::Reverse(snowflake(
+                    p.last_message_id.as_deref().or(Some(p.id.as_str())),
+                ))
+            }),
+            ForumSort::CreationDate => {
+                posts.sort_by_key(|p| std::cmp::Reverse(snowflake(Some(p.id.as_str()))))
+            }
+        }
+
+        posts
+    }
+
+    /// The thread open in the side panel.
+    pub fn open_thread_info(&self) -> Option<&Channel> {
+        let id = self.open_thread.as_ref()?;
+        self.threads.iter().find(|t| &t.id == id)
+    }
+
+    /// DM conversations, most recently active first.
+    pub fn sorted_dm_channels(&self) -> Vec<&Channel> {
+        let mut channels: Vec<_> = self.dm_channels.iter().collect();
+        channels.sort_by_key(|c| {
+            std::cmp::Reverse(
+                c.last_message_id
+                    .as_deref()
+                    .and_then(|id| id.parse::<u64>().ok())
+                    .unwrap_or(0),
+            )
+        });
+        channels
+    }
+
+    /// Turns user and channel names in a search query into ids.
+    pub fn resolve_search(&self, query: &SearchQuery) -> Result<SearchFilters, String> {
+        let users = |names: &[String]| -> Result<Vec<String>, String> {
+            names
+                .iter()
+                .map(|name| {
+                    self.find_user_id(name)
+                        .ok_or_else(|| format!("Unknown user: {}", name))
+                })
+                .collect()
+        };
+        let date = |date: &Option<String>| -> Result<Option<u64>, String> {
+            date.as_deref()
+                .map(|d| {
+                    date_to_snowflake(d)
+                        .ok_or_else(|| format!("Invalid date {}, use YYYY-MM-DD", d))
+                })
+                .transpose()
+        };
+
+        let channel_ids = query
+            .in_channels
+            .iter()
+            .map(|name| {
+                // The current guild first, then anything else we know about
+                self.channels
+                    .iter()
+                    .chain(self.channel_cache.values())
+                    .find(|c| &c.id == name || c.name.as_deref() == Some(name.as_str()))
+                    .map(|c| c.id.clone())
+                    .ok_or_else(|| format!("Unknown channel: #{}", name))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(SearchFilters {
+            content: query.content.clone(),
+            author_ids: users(&query.from)?,
+            mention_ids: users(&query.mentions)?,
+            channel_ids,
+            has: query.has.clone(),
+            max_id: date(&query.before)?,
+            min_id: date(&query.after)?.map(|id| id.saturating_add(SNOWFLAKES_PER_DAY)),
+            pinned: query.pinned,
+        })
+    }
+
+    /// Finds a user by id, username, display name or nickname in the
+    /// current guild.
+    fn find_user_id(&self, name: &str) -> Option<String> {
+        let name = name.trim_start_matches('@');
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_digit()) {
+            return Some(name.to_string());
+        }
+
+        let matches = |user: &User| {
+            user.username.eq_ignore_ascii_case(name)
+                || user
+                    .global_name
+                    .as_deref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+        };
+
+        let members = self
+            .selected_guild
+            .as_ref()
+            .and_then(|id| self.guild_members.get(id));
+        members
+            .into_iter()
+            .flat_map(|m| m.values())
+            .find(|m| {
+                m.nick
+                    .as_deref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                    || m.user.as_ref().is_some_and(matches)
+            })
+            .and_then(|m| m.id().map(str::to_string))
+            .or_else(|| {
+                self.user_cache
+                    .values()
+                    .find(|u| matches(u))
+                    .map(|u| u.id.clone())
+            })
+    }
+
+    /// An opened export archive, by the id of the guild it stands in for.
+    pub fn archive(&self, guild_id: &str) -> Option<&ArchiveGuild> {
+        self.archives.iter().find(|a| a.id == guild_id)
+    }
+
+    /// The archive open in the channel list, if any.
+    pub fn selected_archive(&self) -> Option<&ArchiveGuild> {
+        if self.viewing_dms {
+            return None;
+        }
+        self.archive(self.selected_guild.as_deref()?)
+    }
+
+    /// Whether the channel list shows an archive, so everything is read-only.
+    pub fn viewing_archive(&self) -> bool {
+        self.selected_archive().is_some()
+    }
+
+    /// Name of a guild we're in or of an opened archive.
+    pub fn guild_name(&self, guild_id: &str) -> Option<&str> {
+        self.guilds
+            .iter()
+            .find(|g| g.id == guild_id)
+            .map(|g| g.name.as_str())
+            .or_else(|| self.archive(guild_id).map(|a| a.name.as_str()))
+    }
+
+    /// Looks a channel up in every list we keep, threads included.
+    pub fn channel_by_id(&self, id: &str) -> Option<&Channel> {
+        self.channels
+            .iter()
+            .chain(self.threads.iter())
+            .chain(self.dm_channels.iter())
+            .find(|c| c.id == id)
+            .or_else(|| self.channel_cache.get(id))
+    }
+
+    /// The channel currently open in the chat view, whether guild channel or DM.
+    pub fn selected_channel_info(&self) -> Option<&Channel> {
+        let id = self.selected_channel.as_ref()?;
+        self.channels
+            .iter()
+            .chain(self.dm_channels.iter())
+            .find(|c| &c.id == id)
+    }
+
+    /// Human readable name, using recipients for DMs and unnamed group DMs.
+    pub fn channel_display_name(&self, channel: &Channel) -> String {
+        if let Some(name) = channel.name.as_deref().filter(|n| !n.is_empty()) {
+            return name.to_string();
+        }
+
+        if channel.recipients.is_empty() {
+            return "Unknown".to_string();
+        }
+
+        channel
+            .recipients
+            .iter()
+            .map(|u| u.global_name.as_deref().unwrap_or(&u.username))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The guild a cached channel belongs to, `None` for DMs and unknown
+    /// channels.
+    pub fn guild_for_channel(&self, channel_id: &str) -> Option<String> {
+        self.channel_cache
+            .get(channel_id)
+            .and_then(|c| c.guild_id.clone())
+    }
+
+    /// "Guild › #channel" label used to give inbox entries some context.
+    pub fn mention_context(&self, message: &Message) -> String {
+        if let Some(dm) = self.dm_channels.iter().find(|c| c.id == message.channel_id) {
+            return format!("Direct Messages › @{}", self.channel_display_name(dm));
+        }
+
+        let guild_id = message
+            .guild_id
+            .clone()
+            .or_else(|| self.guild_for_channel(&message.channel_id));
+
+        let guild_name = guild_id
+            .and_then(|id| self.guilds.iter().find(|g| g.id == id))
+            .map(|g| g.name.as_str())
+            .unwrap_or("Unknown server");
+
+        let channel_name = self
+            .channel_cache
+            .get(&message.channel_id)
+            .and_then(|c| c.name.as_deref())
+            .unwrap_or("unknown-channel");
+
+        format!("{} › #{}", guild_name, channel_name)
+    }
+}
@@ -0,0 +1,714 @@
+//! The local cache: an SQLite database per account holding guilds,
+//! channels, users, members and messages, with a full-text index and the
+//! ranges of history fetched without gaps.
+
+use crate::api::{Channel, Guild, GuildMember, Message, User};
+use crate::search::SearchFilters;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Params};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Holds the id of the account that logged in last, so its cache can be
+// browsed before (or without) logging in again
+const LAST_ACCOUNT_FILE: &str = "last_account";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS messages (
+        id INTEGER PRIMARY KEY,
+        channel_id TEXT NOT NULL,
+        guild_id TEXT,
+        author_id TEXT NOT NULL,
+        pinned INTEGER NOT NULL DEFAULT 0,
+        -- Space separated, matched with LIKE '% value %'
+        mention_ids TEXT NOT NULL DEFAULT '',
+        has TEXT NOT NULL DEFAULT '',
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS messages_channel ON messages (channel_id, id);
+    CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(content);
+    -- Inclusive spans of message ids fetched without anything missing
+    -- between them; start_id 0 means the span reaches the channel's start
+    CREATE TABLE IF NOT EXISTS message_ranges (
+        channel_id TEXT NOT NULL,
+        start_id INTEGER NOT NULL,
+        end_id INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS message_ranges_channel ON message_ranges (channel_id, start_id);
+    CREATE TABLE IF NOT EXISTS account (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS guilds (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    -- guild_id is NULL for DMs and group DMs
+    CREATE TABLE IF NOT EXISTS channels (
+        id TEXT PRIMARY KEY,
+        guild_id TEXT,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS users (
+        id TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS members (
+        guild_id TEXT NOT NULL,
+        user_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (guild_id, user_id)
+    );
+";
+
+/// Everything needed to draw the guild and channel lists without a
+/// network round trip.
+pub struct Cache {
+    /// The guild list, in the server's order.
+    pub guilds: Vec<Guild>,
+    /// Direct and group DM channels.
+    pub dm_channels: Vec<Channel>,
+    /// Channels of every cached guild.
+    pub channels: Vec<Channel>,
+    /// Users seen as authors, recipients or members.
+    pub users: Vec<User>,
+    /// Members by guild id, then user id.
+    pub members: HashMap<String, HashMap<String, GuildMember>>,
+}
+
+/// A stretch of a channel's history that was never fetched: the messages
+/// on either side are stored, any between them may be missing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gap {
+    /// Newest stored message before the hole.
+    pub after: u64,
+    /// Oldest stored message after it.
+    pub before: u64,
+}
+
+/// Local database for one account. Guilds, channels, users, members and
+/// every message the client sees are kept here, messages with a full-text
+/// index over their content.
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// Opens the account's database in the platform data directory,
+    /// creating it on first use.
+    /// The account is remembered as the last one used.
+    pub fn open(user_id: &str) -> Result<Store, String> {
+        let dir = data_dir()?;
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+        let store = Store::open_path(&dir.join(format!("{}.db", user_id)))?;
+        if let Err(e) = std::fs::write(dir.join(LAST_ACCOUNT_FILE), user_id) {
+            eprintln!("Failed to remember account: {}", e);
+        }
+        Ok(store)
+    }
+
+    /// Opens the database of the account that logged in last, if any.
+    pub fn open_last() -> Option<Store> {
+        let user_id = std::fs::read_to_string(data_dir().ok()?.join(LAST_ACCOUNT_FILE)).ok()?;
+        match Store::open(user_id.trim()) {
+            Ok(store) => Some(store),
+            Err(e) => {
+                eprintln!("Failed to open cache: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Opens the database at `path`, creating missing tables. `":memory:"`
+    /// gives a throwaway database.
+    pub fn open_path(path: &Path) -> Result<Store, String> {
+        let connection = Connection::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to create tables: {}", e))?;
+
+        Ok(Store { connection })
+    }
+
+    /// Remembers the logged in user, for offline starts.
+    pub fn save_account(&self, user: &User) -> Result<(), String> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO account (id, data) VALUES (0, ?1)",
+                [to_json(user)?],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Failed to save account: {}", e))
+    }
+
+    /// The user saved by [`Store::save_account`].
+    pub fn load_account(&self) -> Option<User> {
+        self.load_json("SELECT data FROM account", []).ok()?.pop()
+    }
+
+    /// Replaces the guild list, keeping the server's order.
+    pub fn save_guilds(&mut self, guilds: &[Guild]) -> Result<(), String> {
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        transaction
+            .execute("DELETE FROM guilds", [])
+            .map_err(|e| format!("Failed to save guilds: {}", e))?;
+        for (position, guild) in guilds.iter().enumerate() {
+            transaction
+                .execute(
+                    "INSERT INTO guilds (id, position, data) VALUES (?1, ?2, ?3)",
+                    params![guild.id, position, to_json(guild)?],
+                )
+                .map_err(|e| format!("Failed to save guilds: {}", e))?;
+        }
+
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to save guilds: {}", e))
+    }
+
+    /// Replaces the channels of a guild, or the DM list when `guild_id` is None.
+    pub fn save_channels(
+        &mut self,
+        guild_id: Option<&str>,
+        channels: &[Channel],
+    ) -> Result<(), String> {
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        transaction
+            .execute("DELETE FROM channels WHERE guild_id IS ?1", [guild_id])
+            .map_err(|e| format!("Failed to save channels: {}", e))?;
+        for channel in channels {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO channels (id, guild_id, data) VALUES (?1, ?2, ?3)",
+                    params![channel.id, guild_id, to_json(channel)?],
+                )
+                .map_err(|e| format!("Failed to save channels: {}", e))?;
+        }
+
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to save channels: {}", e))
+    }
+
+    /// A guild's channels, in the order they were saved.
+    pub fn load_channels(&self, guild_id: &str) -> Vec<Channel> {
+        self.load_json(
+            "SELECT data FROM channels WHERE guild_id = ?1 ORDER BY rowid",
+            [guild_id],
+        )
+        .unwrap_or_default()
+    }
+
+    /// Inserts or updates users.
+    pub fn save_users<'a>(
+        &mut self,
+        users: impl IntoIterator<Item = &'a User>,
+    ) -> Result<(), String> {
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for user in users {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO users (id, data) VALUES (?1, ?2)",
+                    params![user.id, to_json(user)?],
+                )
+                .map_err(|e| format!("Failed to save users: {}", e))?;
+        }
+
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to save users: {}", e))
+    }
+
+    /// Inserts or updates members of a guild.
+    pub fn save_members<'a>(
+        &mut self,
+        guild_id: &str,
+        members: impl IntoIterator<Item = &'a GuildMember>,
+    ) -> Result<(), String> {
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for member in members {
+            let Some(user_id) = member.id() else {
+                continue;
+            };
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO members (guild_id, user_id, data) VALUES (?1, ?2, ?3)",
+                    params![guild_id, user_id, to_json(member)?],
+                )
+                .map_err(|e| format!("Failed to save members: {}", e))?;
+        }
+
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to save members: {}", e))
+    }
+
+    /// Loads everything but messages, which are read per channel.
+    pub fn load_cache(&self) -> Result<Cache, String> {
+        let guilds = self.load_json("SELECT data FROM guilds ORDER BY position", [])?;
+        let dm_channels = self.load_json(
+            "SELECT data FROM channels WHERE guild_id IS NULL ORDER BY rowid",
+            [],
+        )?;
+        let channels = self.load_json(
+            "SELECT data FROM channels WHERE guild_id IS NOT NULL ORDER BY rowid",
+            [],
+        )?;
+        let users = self.load_json("SELECT data FROM users", [])?;
+
+        let mut members: HashMap<String, HashMap<String, GuildMember>> = HashMap::new();
+        let mut statement = self
+            .connection
+            .prepare("SELECT guild_id, user_id, data FROM members")
+            .map_err(|e| format!("Failed to load members: {}", e))?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(|e| format!("Failed to load members: {}", e))?;
+        for (guild_id, user_id, data) in rows.filter_map(|row| row.ok()) {
+            if let Ok(member) = serde_json::from_str(&data) {
+                members.entry(guild_id).or_default().insert(user_id, member);
+            }
+        }
+
+        Ok(Cache {
+            guilds,
+            dm_channels,
+            channels,
+            users,
+            members,
+        })
+    }
+
+    /// The newest `limit` stored messages of a channel, oldest first like
+    /// `api::fetch_messages`.
+    pub fn load_messages(&self, channel_id: &str, limit: u32) -> Vec<Message> {
+        let mut messages: Vec<Message> = self
+            .load_json(
+                "SELECT data FROM messages WHERE channel_id = ?1 ORDER BY id DESC LIMIT ?2",
+                params![channel_id, limit],
+            )
+            .unwrap_or_default();
+        messages.reverse();
+        messages
+    }
+
+    /// Runs a query selecting one JSON column, skipping rows that no longer
+    /// deserialize (e.g. written by an older version).
+    fn load_json<T: DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: impl Params,
+    ) -> Result<Vec<T>, String> {
+        let mut statement = self
+            .connection
+            .prepare(sql)
+            .map_err(|e| format!("Failed to read cache: {}", e))?;
+        let rows = statement
+            .query_map(params, |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to read cache: {}", e))?
+            .filter_map(|data| data.ok())
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect();
+        Ok(rows)
+    }
+
+    /// Inserts or refreshes messages and their index entries. Ranges are
+    /// left alone: a lone search hit or pin says nothing about its
+    /// neighbours, only [`Store::add_range`] does.
+    pub fn save_messages(&mut self, messages: &[Message]) -> Result<(), String> {
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for message in messages {
+            let Ok(id) = message.id.parse::<i64>() else {
+                continue;
+            };
+            let data = to_json(message)?;
+            let mention_ids: Vec<&str> = message.mentions.iter().map(|u| u.id.as_str()).collect();
+
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO messages
+                        (id, channel_id, guild_id, author_id, pinned, mention_ids, has, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        id,
+                        message.channel_id,
+                        message.guild_id,
+                        message.author.id,
+                        message.pinned,
+                        mention_ids.join(" "),
+                        has_tokens(message).join(" "),
+                        data,
+                    ],
+                )
+                .and_then(|_| {
+                    transaction.execute("DELETE FROM messages_fts WHERE rowid = ?1", [id])
+                })
+                .and_then(|_| {
+                    transaction.execute(
+                        "INSERT INTO messages_fts (rowid, content) VALUES (?1, ?2)",
+                        params![id, message.content],
+                    )
+                })
+                .map_err(|e| format!("Failed to save message: {}", e))?;
+        }
+
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to save messages: {}", e))
+    }
+
+    /// Records that a channel has no missing messages from `start` to `end`.
+    pub fn add_range(&mut self, channel_id: &str, start: u64, end: u64) -> Result<(), String> {
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        merge_range(&transaction, channel_id, start as i64, end as i64)
+            .and_then(|_| transaction.commit())
+            .map_err(|e| format!("Failed to save message range: {}", e))
+    }
+
+    /// Holes between the known ranges of a channel, oldest first.
+    pub fn gaps(&self, channel_id: &str) -> Vec<Gap> {
+        let ranges: Vec<(i64, i64)> = self
+            .connection
+            .prepare(
+                "SELECT start_id, end_id FROM message_ranges
+                 WHERE channel_id = ?1 ORDER BY start_id",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map([channel_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        ranges
+            .windows(2)
+            .map(|pair| Gap {
+                after: pair[0].1 as u64,
+                before: pair[1].0 as u64,
+            })
+            .collect()
+    }
+
+    /// Searches stored messages across every guild and DM. Text matches are
+    /// ranked by relevance, filter-only searches newest first.
+    pub fn search(
+        &self,
+        filters: &SearchFilters,
+        offset: u32,
+        limit: u32,
+    ) -> Result<(u32, Vec<Message>), String> {
+        let mut join = "";
+        let mut order = "messages.id DESC";
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+
+        let fts_query = fts_query(&filters.content);
+        if !fts_query.is_empty() {
+            join = "JOIN messages_fts ON messages_fts.rowid = messages.id";
+            order = "bm25(messages_fts)";
+            clauses.push("messages_fts MATCH ?".to_string());
+            values.push(Value::Text(fts_query));
+        }
+
+        let mut any_of = |column: &str, ids: &[String]| {
+            if !ids.is_empty() {
+                clauses.push(format!(
+                    "{} IN ({})",
+                    column,
+                    vec!["?"; ids.len()].join(", ")
+                ));
+                values.extend(ids.iter().cloned().map(Value::Text));
+            }
+        };
+        any_of("author_id", &filters.author_ids);
+        any_of("channel_id", &filters.channel_ids);
+
+        for (column, wanted) in filters
+            .mention_ids
+            .iter()
+            .map(|id| ("mention_ids", id))
+            .chain(filters.has.iter().map(|has| ("has", has)))
+        {
+            clauses.push(format!("(' ' || {} || ' ') LIKE ?", column));
+            values.push(Value::Text(format!("% {} %", wanted)));
+        }
+
+        if let Some(max_id) = filters.max_id {
+            clauses.push("messages.id < ?".to_string());
+            values.push(Value::Integer(max_id as i64));
+        }
+        if let Some(min_id) = filters.min_id {
+            clauses.push("messages.id >= ?".to_string());
+            values.push(Value::Integer(min_id as i64));
+        }
+        if let Some(pinned) = filters.pinned {
+            clauses.push("pinned = ?".to_string());
+            values.push(Value::Integer(pinned.into()));
+        }
+
+        let filter = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let total: u32 = self
+            .connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM messages {} {}", join, filter),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to count results: {}", e))?;
+
+        values.push(Value::Integer(limit.into()));
+        values.push(Value::Integer(offset.into()));
+        let messages = self.load_json(
+            &format!(
+                "SELECT data FROM messages {} {} ORDER BY {} LIMIT ? OFFSET ?",
+                join, filter, order
+            ),
+            params_from_iter(values.iter()),
+        )?;
+
+        Ok((total, messages))
+    }
+}
+
+/// Inserts a range, folding in every range it overlaps.
+fn merge_range(
+    connection: &Connection,
+    channel_id: &str,
+    start: i64,
+    end: i64,
+) -> rusqlite::Result<()> {
+    let (overlap_start, overlap_end): (Option<i64>, Option<i64>) = connection.query_row(
+        "SELECT MIN(start_id), MAX(end_id) FROM message_ranges
+         WHERE channel_id = ?1 AND start_id <= ?3 AND end_id >= ?2",
+        params![channel_id, start, end],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    connection.execute(
+        "DELETE FROM message_ranges WHERE channel_id = ?1 AND start_id <= ?3 AND end_id >= ?2",
+        params![channel_id, start, end],
+    )?;
+    connection.execute(
+        "INSERT INTO message_ranges (channel_id, start_id, end_id) VALUES (?1, ?2, ?3)",
+        params![
+            channel_id,
+            overlap_start.map_or(start, |s| s.min(start)),
+            overlap_end.map_or(end, |e| e.max(end)),
+        ],
+    )?;
+    Ok(())
+}
+
+/// The directory holding the account databases.
+pub fn data_dir() -> Result<PathBuf, String> {
+    Ok(dirs::data_dir()
+        .ok_or("No data directory on this platform")?
+        .join("discord-lite"))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to serialize: {}", e))
+}
+
+/// Values matched by `has:` filters.
+fn has_tokens(message: &Message) -> Vec<&'static str> {
+    let mut tokens = Vec::new();
+
+    if message.content.contains("http://") || message.content.contains("https://") {
+        tokens.push("link");
+    }
+    if !message.attachments.is_empty() {
+        tokens.push("file");
+    }
+    for attachment in &message.attachments {
+        let kind = match attachment.content_type.as_deref() {
+            Some(t) if t.starts_with("image/") => "image",
+            Some(t) if t.starts_with("video/") => "video",
+            Some(t) if t.starts_with("audio/") => "sound",
+            _ => continue,
+        };
+        if !tokens.contains(&kind) {
+            tokens.push(kind);
+        }
+    }
+
+    tokens
+}
+
+/// Turns free text into an FTS5 query: every word must match, the last one
+/// as a prefix so results show up while typing.
+fn fts_query(content: &str) -> String {
+    let words: Vec<String> = content
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+
+    match words.split_last() {
+        Some((last, rest)) => {
+            let mut query = rest.join(" ");
+            if !query.is_empty() {
+                query.push(' ');
+            }
+            query.push_str(last);
+            query.push('*');
+            query
+        }
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANNEL: &str = "10";
+
+    fn store() -> Store {
+        Store::open_path(Path::new(":memory:")).unwrap()
+    }
+
+    fn ranges(store: &Store) -> Vec<(i64, i64)> {
+        store
+            .connection
+            .prepare("SELECT start_id, end_id FROM message_ranges ORDER BY start_id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn gap(after: u64, before: u64) -> Gap {
+        Gap { after, before }
+    }
+
+    #[test]
+    fn disjoint_ranges_leave_gaps() {
+        let mut store = store();
+        store.add_range(CHANNEL, 100, 200).unwrap();
+        store.add_range(CHANNEL, 500, 600).unwrap();
+        store.add_range(CHANNEL, 300, 400).unwrap();
+
+        assert_eq!(ranges(&store), [(100, 200), (300, 400), (500, 600)]);
+        assert_eq!(store.gaps(CHANNEL), [gap(200, 300), gap(400, 500)]);
+    }
+
+    #[test]
+    fn overlapping_ranges_merge() {
+        let mut store = store();
+        store.add_range(CHANNEL, 100, 300).unwrap();
+        store.add_range(CHANNEL, 500, 700).unwrap();
+        // Bridges both, so the gap between them closes
+        store.add_range(CHANNEL, 250, 550).unwrap();
+
+        assert_eq!(ranges(&store), [(100, 700)]);
+        assert!(store.gaps(CHANNEL).is_empty());
+    }
+
+    #[test]
+    fn touching_ranges_merge() {
+        let mut store = store();
+        store.add_range(CHANNEL, 100, 200).unwrap();
+        store.add_range(CHANNEL, 200, 300).unwrap();
+
+        assert_eq!(ranges(&store), [(100, 300)]);
+    }
+
+    #[test]
+    fn contained_ranges_change_nothing() {
+        let mut store = store();
+        store.add_range(CHANNEL, 100, 900).unwrap();
+        store.add_range(CHANNEL, 300, 400).unwrap();
+        assert_eq!(ranges(&store), [(100, 900)]);
+
+        // And a range containing others swallows them
+        store.add_range(CHANNEL, 1000, 1100).unwrap();
+        store.add_range(CHANNEL, 50, 1200).unwrap();
+        assert_eq!(ranges(&store), [(50, 1200)]);
+    }
+
+    #[test]
+    fn short_page_reaches_the_start() {
+        let mut store = store();
+        store.add_range(CHANNEL, 800, 900).unwrap();
+        // A short page below the gap: nothing older exists
+        store.add_range(CHANNEL, 0, 400).unwrap();
+        assert_eq!(store.gaps(CHANNEL), [gap(400, 800)]);
+
+        // Backfilling up to the newer side closes it
+        store.add_range(CHANNEL, 0, 800).unwrap();
+        assert_eq!(ranges(&store), [(0, 900)]);
+        assert!(store.gaps(CHANNEL).is_empty());
+    }
+
+    #[test]
+    fn channels_are_tracked_separately() {
+        let mut store = store();
+        store.add_range(CHANNEL, 100, 200).unwrap();
+        store.add_range("20", 150, 300).unwrap();
+        store.add_range(CHANNEL, 400, 500).unwrap();
+
+        assert_eq!(store.gaps(CHANNEL), [gap(200, 400)]);
+        assert!(store.gaps("20").is_empty());
+    }
+
+    #[test]
+    fn lone_messages_add_no_range() {
+        let mut store = store();
+        store.add_range(CHANNEL, 800, 900).unwrap();
+
+        // A search hit or pin far below the fetched window
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "id": "100",
+            "channel_id": CHANNEL,
+            "content": "old news",
+            "author": { "id": "1", "username": "alice", "discriminator": "0" },
+            "timestamp": "2015-01-01T00:00:00+00:00",
+        }))
+        .unwrap();
+        store.save_messages(&[message]).unwrap();
+
+        assert_eq!(ranges(&store), [(800, 900)]);
+        assert!(store.gaps(CHANNEL).is_empty());
+    }
+}
@@ -0,0 +1,474 @@
+use crate::api::{self, ApiError, Channel, Message, StatusCode};
+use crate::export::{self, ExportFormat, ExportJob, Progress};
+use crate::state::{AppState, UserStatus};
+use crate::tail;
+use crate::tui;
+use clap::{Parser, Subcommand, ValueEnum};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::io::{ErrorKind, Read as _, Write as _};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Exit codes, 2 being clap's own for usage errors
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_NO_TOKEN: i32 = 3;
+const EXIT_NETWORK: i32 = 4;
+const EXIT_UNAVAILABLE: i32 = 5;
+
+const TOKEN_ENV: &str = "DISCORD_TOKEN";
+const CONFIG_FILE: &str = "config.json";
+
+const AFTER_HELP: &str = "\
+Without a command the graphical client starts.
+
+The token is read from the DISCORD_TOKEN environment variable, or from the
+\"token\" field of discord-lite/config.json in your config directory.
+
+Exit codes:
+  0  success
+  1  other failure
+  2  invalid arguments
+  3  missing or rejected token
+  4  network error
+  5  channel or guild not found or not accessible";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "discord-lite",
+    version,
+    about = "A lightweight Discord client",
+    after_help = AFTER_HELP
+)]
+pub struct Cli {
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Send a message, read from stdin when the content is omitted or "-"
+    Send {
+        channel_id: String,
+        content: Option<String>,
+    },
+    /// Print the latest messages of a channel, oldest first
+    Read {
+        channel_id: String,
+        #[arg(long, default_value_t = api::MESSAGE_PAGE)]
+        limit: usize,
+    },
+    /// Stream new messages of one or more channels, until interrupted
+    Tail {
+        #[arg(required = true)]
+        channel_ids: Vec<String>,
+        /// Only messages from this user id or username
+        #[arg(long)]
+        author: Vec<String>,
+        /// Only messages whose content matches this regular expression
+        #[arg(long = "match", value_parser = Regex::new)]
+        pattern: Option<Regex>,
+        /// Only messages mentioning this user id, or "me"
+        #[arg(long)]
+        mention: Vec<String>,
+        /// Poll the REST API instead of listening on the gateway
+        #[arg(long)]
+        poll: bool,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 5, requires = "poll")]
+        interval: u64,
+    },
+    /// List the guilds you are in
+    Guilds,
+    /// List the channels of a guild
+    Channels { guild_id: String },
+    /// Export the history of a channel, thread or DM
+    Export {
+        channel_id: String,
+        /// Formats to write, all of them by default
+        #[arg(long = "format", value_enum)]
+        formats: Vec<Format>,
+        /// First day to include, YYYY-MM-DD
+        #[arg(long)]
+        since: Option<String>,
+        /// Last day to include, YYYY-MM-DD
+        #[arg(long)]
+        until: Option<String>,
+        /// Directory to write to, under your documents folder by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Open the terminal interface
+    Tui,
+    /// Show the account and its status, or change the status
+    Status {
+        #[arg(value_parser = ["online", "idle", "dnd", "invisible"])]
+        status: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Format {
+    Json,
+    Html,
+    Text,
+}
+
+impl From<Format> for ExportFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Json => ExportFormat::Json,
+            Format::Html => ExportFormat::Html,
+            Format::Text => ExportFormat::Text,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Config {
+    #[serde(default)]
+    token: Option<String>,
+}
+
+/// Runs a command to completion and returns the process exit code.
+pub fn run(command: CliCommand, json: bool) -> i32 {
+    // The terminal interface has its own login screen for a missing token
+    if let CliCommand::Tui = command {
+        return tui::run(load_token());
+    }
+
+    let Some(token) = load_token() else {
+        eprintln!(
+            "No token found: set {} or add \"token\" to {}",
+            TOKEN_ENV,
+            config_path().map_or(CONFIG_FILE.into(), |p| p.display().to_string())
+        );
+        return EXIT_NO_TOKEN;
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start runtime: {}", e);
+            return EXIT_FAILURE;
+        }
+    };
+
+    match runtime.block_on(execute(token, command, json)) {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit_code(&e)
+        }
+    }
+}
+
+async fn execute(token: String, command: CliCommand, json: bool) -> Result<(), ApiError> {
+    match command {
+        CliCommand::Send {
+            channel_id,
+            content,
+        } => {
+            let content = match content.filter(|c| c != "-") {
+                Some(content) => content,
+                None => {
+                    let mut content = String::new();
+                    std::io::stdin()
+                        .read_to_string(&mut content)
+                        .map_err(|e| ApiError::Other(format!("Failed to read stdin: {}", e)))?;
+                    content.trim_end().to_string()
+                }
+            };
+            if content.is_empty() {
+                return Err(ApiError::Other("Nothing to send".to_string()));
+            }
+            api::send_message(token, channel_id, content).await
+        }
+        CliCommand::Read { channel_id, limit } => {
+            let messages = fetch_latest(token, channel_id, limit).await?;
+            if json {
+                print_json(&messages);
+            } else {
+                for message in &messages {
+                    print_message(message);
+                }
+            }
+            Ok(())
+        }
+        CliCommand::Tail {
+            channel_ids,
+            author,
+            pattern,
+            mention,
+            poll,
+            interval,
+        } => {
+            let mut mentions = mention;
+            if mentions.iter().any(|m| m == "me") {
+                let user = api::verify_token(token.clone()).await?;
+                for mention in mentions.iter_mut().filter(|m| *m == "me") {
+                    mention.clone_from(&user.id);
+                }
+            }
+
+            // Label lines with the channel only when following several
+            let labelled = channel_ids.len() > 1;
+            let state = AppState::new();
+            let mut channels = Vec::new();
+            for channel_id in channel_ids {
+                let channel = api::fetch_channel(token.clone(), channel_id).await?;
+                let label = match (labelled, &channel.guild_id) {
+                    (false, _) => String::new(),
+                    (true, Some(_)) => format!("#{}", state.channel_display_name(&channel)),
+                    (true, None) => format!("@{}", state.channel_display_name(&channel)),
+                };
+                channels.push((channel.id, label));
+            }
+
+            let filter = tail::Filter {
+                authors: author,
+                pattern,
+                mentions,
+            };
+            let source = if poll {
+                tail::Source::Poll(Duration::from_secs(interval.max(1)))
+            } else {
+                tail::Source::Gateway
+            };
+            tail::follow(token, channels, filter, source, json).await
+        }
+        CliCommand::Guilds => {
+            let guilds = api::fetch_guilds(token).await?;
+            if json {
+                print_json(&guilds);
+            } else {
+                for guild in &guilds {
+                    write_line(format_args!("{}  {}", guild.id, guild.name));
+                }
+            }
+            Ok(())
+        }
+        CliCommand::Channels { guild_id } => {
+            let channels = api::fetch_channels(token, guild_id).await?;
+            if json {
+                print_json(&channels);
+            } else {
+                let state = AppState::new();
+                for channel in &channels {
+                    write_line(format_args!(
+                        "{}  {:<12}  {}",
+                        channel.id,
+                        channel_kind(channel),
+                        state.channel_display_name(channel)
+                    ));
+                }
+            }
+            Ok(())
+        }
+        CliCommand::Export {
+            channel_id,
+            formats,
+            since,
+            until,
+            output,
+        } => {
+            let (since, until) = export::date_bounds(
+                since.as_deref().unwrap_or(""),
+                until.as_deref().unwrap_or(""),
+            )
+            .map_err(ApiError::Other)?;
+            let channel = api::fetch_channel(token.clone(), channel_id).await?;
+            let channel_name = AppState::new().channel_display_name(&channel);
+            let guild_name = match &channel.guild_id {
+                Some(guild_id) => api::fetch_guilds(token.clone())
+                    .await?
+                    .into_iter()
+                    .find(|g| &g.id == guild_id)
+                    .map(|g| g.name),
+                None => None,
+            };
+            let directory =
+                output.unwrap_or_else(|| export::default_directory(&channel_name, &channel.id));
+            let formats = if formats.is_empty() {
+                ExportFormat::ALL.to_vec()
+            } else {
+                formats.into_iter().map(ExportFormat::from).collect()
+            };
+
+            let job = ExportJob {
+                channel,
+                channel_name,
+                guild_name,
+                formats,
+                since,
+                until,
+                directory,
+            };
+            let (count, directory) = export::export(token, job, |progress| match progress {
+                Progress::Fetched(count) => eprint!("\rFetched {} messages", count),
+                Progress::Downloading(n, total) => {
+                    eprint!("\rDownloading attachment {} of {}", n, total)
+                }
+                Progress::Finished(_) => {}
+            })
+            .await?;
+            eprintln!();
+
+            if json {
+                print_json(&json!({ "messages": count, "directory": directory }));
+            } else {
+                write_line(format_args!(
+                    "Exported {} messages to {}",
+                    count,
+                    directory.display()
+                ));
+            }
+            Ok(())
+        }
+        CliCommand::Status {
+            status: Some(status),
+        } => {
+            // Only the values accepted by the parser get here
+            let status = UserStatus::from_api(&status)
+                .ok_or_else(|| ApiError::Other("Unknown status".to_string()))?;
+            api::set_status(token, status).await
+        }
+        CliCommand::Tui => unreachable!("handled before the token is required"),
+        CliCommand::Status { status: None } => {
+            let user = api::verify_token(token.clone()).await?;
+            let status = api::fetch_user_settings(token)
+                .await
+                .ok()
+                .and_then(|settings| settings.status)
+                .and_then(|status| UserStatus::from_api(&status));
+            if json {
+                print_json(&json!({
+                    "user": user,
+                    "status": status.as_ref().map(|s| s.as_str()),
+                }));
+            } else {
+                write_line(format_args!(
+                    "{} (@{}, {})",
+                    user.global_name.as_deref().unwrap_or(&user.username),
+                    user.username,
+                    user.id
+                ));
+                if let Some(status) = status {
+                    write_line(format_args!("Status: {}", status.display_name()));
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The last `limit` messages, oldest first, paging back as needed.
+async fn fetch_latest(
+    token: String,
+    channel_id: String,
+    limit: usize,
+) -> Result<Vec<Message>, ApiError> {
+    let mut messages = api::fetch_messages(token.clone(), channel_id.clone()).await?;
+    let mut exhausted = messages.len() < api::MESSAGE_PAGE;
+
+    while messages.len() < limit && !exhausted {
+        let Some(oldest) = messages.first() else {
+            break;
+        };
+        let mut page =
+            api::fetch_messages_before(token.clone(), channel_id.clone(), oldest.id.clone())
+                .await?;
+        exhausted = page.len() < api::MAX_MESSAGE_PAGE;
+        page.append(&mut messages);
+        messages = page;
+    }
+
+    let skip = messages.len().saturating_sub(limit);
+    messages.drain(..skip);
+    Ok(messages)
+}
+
+/// Writes a line to stdout. Output is meant to be piped, so a reader that
+/// stops early (`| head -1`) ends the command quietly instead of panicking.
+pub fn write_line(line: impl fmt::Display) {
+    let mut stdout = std::io::stdout().lock();
+    if let Err(e) = writeln!(stdout, "{}", line) {
+        if e.kind() == ErrorKind::BrokenPipe {
+            std::process::exit(EXIT_OK);
+        }
+        eprintln!("Failed to write output: {}", e);
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+fn print_message(message: &Message) {
+    write_line(export::text_line(message));
+    for attachment in &message.attachments {
+        write_line(format_args!("    [attachment] {}", attachment.url));
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => write_line(json),
+        Err(e) => eprintln!("Failed to serialize output: {}", e),
+    }
+}
+
+fn channel_kind(channel: &Channel) -> &'static str {
+    match channel.channel_type {
+        0 => "text",
+        2 => "voice",
+        4 => "category",
+        5 => "announcement",
+        13 => "stage",
+        15 => "forum",
+        _ => "other",
+    }
+}
+
+/// `DISCORD_TOKEN`, falling back to the config file.
+fn load_token() -> Option<String> {
+    if let Some(token) = std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|t| !t.trim().is_empty())
+    {
+        return Some(token.trim().to_string());
+    }
+
+    let contents = std::fs::read_to_string(config_path()?).ok()?;
+    let config: Config = match serde_json::from_str(&contents) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to parse {}: {}", CONFIG_FILE, e);
+            return None;
+        }
+    };
+    config
+        .token
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("discord-lite").join(CONFIG_FILE))
+}
+
+fn exit_code(error: &ApiError) -> i32 {
+    match error {
+        ApiError::Network(_) => EXIT_NETWORK,
+        ApiError::Status { status, .. } => match *status {
+            StatusCode::UNAUTHORIZED => EXIT_NO_TOKEN,
+            StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => EXIT_UNAVAILABLE,
+            _ => EXIT_FAILURE,
+        },
+        ApiError::RateLimited { .. } | ApiError::Other(_) => EXIT_FAILURE,
+    }
+}
@@ -1,50 +1,30 @@
-use iced::{executor, Application, Command, Element, Settings, Theme};
+use iced::{executor, Application, Command, Element, Settings, Subscription, Theme};
 
-mod api;
-mod state;
+mod cli;
+mod subscriptions;
+mod tail;
+mod tui;
 mod ui;
 
-use state::AppState;
+use app::{App, Effect, Message};
+use discord_lite_core::{api, app, export, gateway, permissions, search, state, store};
 use ui::view;
 
+/// The window: [`App`] run by iced, its effects turned into commands.
 pub struct DiscordLite {
-    state: AppState,
+    app: App,
+    // Avatar handles, made once per download rather than on every view
+    avatars: ui::Avatars,
 }
 
-#[derive(Debug, Clone)]
-pub enum Message {
-    // Login
-    TokenInputChanged(String),
-    Login,
-    LoginResult(Result<api::User, String>),
-
-    // Guild & Channel selection
-    GuildsLoaded(Result<Vec<api::Guild>, String>),
-    SelectGuild(String),
-    ChannelsLoaded(Result<Vec<api::Channel>, String>),
-    SelectChannel(String),
-
-    // Messages
-    MessagesLoaded(Result<Vec<api::Message>, String>),
-    MessageInputChanged(String),
-    SendMessage,
-    MessageSent(Result<(), String>),
-
-    // Status
-    ToggleStatusMenu,
-    ChangeStatus(state::UserStatus),
-    StatusChanged(Result<(), String>),
-
-    // Profile
-    OpenProfileEditor,
-    CloseProfileEditor,
-    ProfileBioInputChanged(String),
-    ProfileDisplayNameInputChanged(String),
-    SaveProfile,
-    ProfileSaved(Result<(), String>),
-    ViewUserProfile(String),
-    UserProfileLoaded(Result<api::UserProfile, String>),
-    CloseUserProfile,
+/// Runs each future of an effect as a command yielding its message.
+fn command(effect: Effect) -> Command<Message> {
+    Command::batch(
+        effect
+            .into_futures()
+            .into_iter()
+            .map(|future| Command::perform(future, std::convert::identity)),
+    )
 }
 
 impl Application for DiscordLite {
@@ -56,7 +36,8 @@ impl Application for DiscordLite {
     fn new(_flags: ()) -> (Self, Command<Message>) {
         (
             DiscordLite {
-                state: AppState::new(),
+                app: App::new(),
+                avatars: ui::Avatars::new(),
             },
             Command::none(),
         )
@@ -67,238 +48,21 @@ impl Application for DiscordLite {
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
-        match message {
-            Message::TokenInputChanged(token) => {
-                self.state.token_input = token;
-                Command::none()
-            }
-
-            Message::Login => {
-                let token = self.state.token_input.clone();
-                Command::perform(api::verify_token(token.clone()), move |result| {
-                    Message::LoginResult(result.map(|user| user))
-                })
-            }
-
-            Message::LoginResult(Ok(user)) => {
-                self.state.logged_in = true;
-                self.state.current_user = Some(user);
-                self.state.token = Some(self.state.token_input.clone());
-
-                let token = self.state.token.clone().unwrap();
-                Command::perform(api::fetch_guilds(token), Message::GuildsLoaded)
-            }
-
-            Message::LoginResult(Err(e)) => {
-                self.state.error = Some(format!("Login failed: {}", e));
-                Command::none()
-            }
-
-            Message::GuildsLoaded(Ok(guilds)) => {
-                self.state.guilds = guilds;
-                self.state.error = None;
-                Command::none()
-            }
-
-            Message::GuildsLoaded(Err(e)) => {
-                self.state.error = Some(format!("Failed to load guilds: {}", e));
-                Command::none()
-            }
-
-            Message::SelectGuild(guild_id) => {
-                self.state.selected_guild = Some(guild_id.clone());
-                self.state.selected_channel = None;
-                self.state.messages.clear();
-
-                let token = self.state.token.clone().unwrap();
-                Command::perform(
-                    api::fetch_channels(token, guild_id),
-                    Message::ChannelsLoaded,
-                )
-            }
-
-            Message::ChannelsLoaded(Ok(channels)) => {
-                self.state.channels = channels;
-                self.state.error = None;
-                Command::none()
-            }
-
-            Message::ChannelsLoaded(Err(e)) => {
-                self.state.error = Some(format!("Failed to load channels: {}", e));
-                Command::none()
-            }
-
-            Message::SelectChannel(channel_id) => {
-                self.state.selected_channel = Some(channel_id.clone());
-
-                let token = self.state.token.clone().unwrap();
-                Command::perform(
-                    api::fetch_messages(token, channel_id),
-                    Message::MessagesLoaded,
-                )
-            }
-
-            Message::MessagesLoaded(Ok(messages)) => {
-                self.state.messages = messages;
-                self.state.error = None;
-                Command::none()
-            }
-
-            Message::MessagesLoaded(Err(e)) => {
-                self.state.error = Some(format!("Failed to load messages: {}", e));
-                Command::none()
-            }
-
-            Message::MessageInputChanged(text) => {
-                self.state.message_input = text;
-                Command::none()
-            }
-
-            Message::SendMessage => {
-                if self.state.message_input.trim().is_empty() {
-                    return Command::none();
-                }
-
-                let token = self.state.token.clone().unwrap();
-                let channel_id = self.state.selected_channel.clone().unwrap();
-                let content = self.state.message_input.clone();
-
-                self.state.message_input.clear();
-
-                Command::perform(
-                    api::send_message(token.clone(), channel_id.clone(), content),
-                    move |result| Message::MessageSent(result),
-                )
-            }
-
-            Message::MessageSent(Ok(())) => {
-                // Refresh messages after sending
-                let token = self.state.token.clone().unwrap();
-                let channel_id = self.state.selected_channel.clone().unwrap();
-
-                Command::perform(
-                    api::fetch_messages(token, channel_id),
-                    Message::MessagesLoaded,
-                )
-            }
-
-            Message::MessageSent(Err(e)) => {
-                self.state.error = Some(format!("Failed to send message: {}", e));
-                Command::none()
-            }
-
-            Message::ToggleStatusMenu => {
-                self.state.status_menu_open = !self.state.status_menu_open;
-                Command::none()
-            }
-
-            Message::ChangeStatus(new_status) => {
-                self.state.status_menu_open = false;
-                self.state.current_status = new_status;
-
-                let token = self.state.token.clone().unwrap();
-                Command::perform(api::set_status(token, new_status), Message::StatusChanged)
-            }
-
-            Message::StatusChanged(Ok(())) => {
-                self.state.error = None;
-                Command::none()
-            }
-
-            Message::StatusChanged(Err(e)) => {
-                self.state.error = Some(format!("Failed to change status: {}", e));
-                Command::none()
-            }
-
-            Message::OpenProfileEditor => {
-                // Pre-fill inputs with current values
-                if let Some(user) = &self.state.current_user {
-                    self.state.profile_display_name_input =
-                        user.global_name.clone().unwrap_or_default();
-                    self.state.profile_bio_input = user.bio.clone().unwrap_or_default();
-                }
-                self.state.show_profile_editor = true;
-                Command::none()
-            }
-
-            Message::CloseProfileEditor => {
-                self.state.show_profile_editor = false;
-                Command::none()
-            }
-
-            Message::ProfileBioInputChanged(text) => {
-                self.state.profile_bio_input = text;
-                Command::none()
-            }
-
-            Message::ProfileDisplayNameInputChanged(text) => {
-                self.state.profile_display_name_input = text;
-                Command::none()
-            }
-
-            Message::SaveProfile => {
-                let token = self.state.token.clone().unwrap();
-                let bio = if self.state.profile_bio_input.trim().is_empty() {
-                    None
-                } else {
-                    Some(self.state.profile_bio_input.clone())
-                };
-                let display_name = if self.state.profile_display_name_input.trim().is_empty() {
-                    None
-                } else {
-                    Some(self.state.profile_display_name_input.clone())
-                };
-
-                Command::perform(
-                    api::update_profile(token, bio, display_name),
-                    Message::ProfileSaved,
-                )
-            }
-
-            Message::ProfileSaved(Ok(())) => {
-                self.state.show_profile_editor = false;
-                self.state.error = None;
-
-                // Re-fetch user data to update display
-                let token = self.state.token.clone().unwrap();
-                Command::perform(api::verify_token(token), |result| {
-                    Message::LoginResult(result.map(|user| user))
-                })
-            }
-
-            Message::ProfileSaved(Err(e)) => {
-                self.state.error = Some(format!("Failed to save profile: {}", e));
-                Command::none()
-            }
-
-            Message::ViewUserProfile(user_id) => {
-                let token = self.state.token.clone().unwrap();
-                Command::perform(
-                    api::fetch_user_profile(token, user_id),
-                    Message::UserProfileLoaded,
-                )
-            }
-
-            Message::UserProfileLoaded(Ok(profile)) => {
-                self.state.viewing_user_profile = Some(profile);
-                self.state.error = None;
-                Command::none()
-            }
-
-            Message::UserProfileLoaded(Err(e)) => {
-                self.state.error = Some(format!("Failed to load user profile: {}", e));
-                Command::none()
-            }
-
-            Message::CloseUserProfile => {
-                self.state.viewing_user_profile = None;
-                Command::none()
-            }
+        if let Message::AvatarLoaded(key, Ok(bytes)) = &message {
+            self.avatars.insert(
+                key.clone(),
+                iced::widget::image::Handle::from_memory(bytes.clone()),
+            );
         }
+        command(self.app.update(message))
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        subscriptions::session(&self.app.state)
     }
 
-    fn view(&self) -> Element<Message> {
-        view(&self.state)
+    fn view(&self) -> Element<'_, Message> {
+        view(&self.app.state, &self.avatars)
     }
 
     fn theme(&self) -> Theme {
@@ -307,6 +71,12 @@ impl Application for DiscordLite {
 }
 
 fn main() -> iced::Result {
+    // Any subcommand runs headless instead of opening the window
+    let cli = <cli::Cli as clap::Parser>::parse();
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run(command, cli.json));
+    }
+
     DiscordLite::run(Settings {
         window: iced::window::Settings {
             size: [1200.0, 800.0].into(),
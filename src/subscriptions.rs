@@ -0,0 +1,54 @@
+use crate::export::{self, ExportJob, Progress};
+use crate::gateway::{self, Event};
+use crate::state::AppState;
+use crate::Message;
+use iced::futures::SinkExt;
+use iced::{subscription, Subscription};
+use std::time::Duration;
+
+/// What a session listens to: the gateway while logged in, a running
+/// export, and a clock while a slowmode cooldown counts down.
+pub fn session(state: &AppState) -> Subscription<Message> {
+    let Some(token) = &state.token else {
+        return Subscription::none();
+    };
+
+    let mut subscriptions = vec![gateway(token.clone()).map(Message::Gateway)];
+
+    if let Some(job) = &state.export_job {
+        subscriptions.push(export(token.clone(), job.clone()).map(Message::ExportProgress));
+    }
+
+    // Only tick while there is something that expires
+    if !state.slowmode_until.is_empty() {
+        subscriptions.push(iced::time::every(Duration::from_secs(1)).map(Message::Tick));
+    }
+
+    Subscription::batch(subscriptions)
+}
+
+/// Keeps a gateway connection open for as long as the subscription is alive.
+pub fn gateway(token: String) -> Subscription<Event> {
+    struct Gateway;
+
+    subscription::channel(std::any::TypeId::of::<Gateway>(), 100, |output| {
+        gateway::listen(token, output)
+    })
+}
+
+/// Runs an export in the background for as long as the subscription is
+/// alive, reporting progress along the way.
+pub fn export(token: String, job: ExportJob) -> Subscription<Progress> {
+    subscription::channel(job.directory.clone(), 100, |mut output| async move {
+        let result = export::export(token, job, |progress| {
+            // Progress is cosmetic, drop it if the UI falls behind
+            let _ = output.try_send(progress);
+        })
+        .await;
+        let _ = output.send(Progress::Finished(result)).await;
+
+        loop {
+            std::future::pending::<()>().await;
+        }
+    })
+}
@@ -0,0 +1,178 @@
+use crate::api::{self, ApiError, Message};
+use crate::cli::write_line;
+use crate::export;
+use crate::gateway::{self, Event};
+use futures::channel::mpsc;
+use futures::StreamExt;
+use regex::Regex;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Which messages get printed. Each set filter has to match, and a list
+/// matches when any of its entries does.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    // User ids or usernames
+    pub authors: Vec<String>,
+    pub pattern: Option<Regex>,
+    // User ids mentioned directly or through @everyone
+    pub mentions: Vec<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, message: &Message) -> bool {
+        let author = &message.author;
+        let author_matches = self.authors.is_empty()
+            || self
+                .authors
+                .iter()
+                .any(|a| a == &author.id || a.eq_ignore_ascii_case(&author.username));
+
+        let pattern_matches = self
+            .pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&message.content));
+
+        let mention_matches = self.mentions.is_empty()
+            || message.mention_everyone
+            || message
+                .mentions
+                .iter()
+                .any(|user| self.mentions.contains(&user.id));
+
+        author_matches && pattern_matches && mention_matches
+    }
+}
+
+/// Where new messages come from.
+#[derive(Debug, Clone, Copy)]
+pub enum Source {
+    Gateway,
+    // Polling `after=` at this interval
+    Poll(Duration),
+}
+
+/// A followed channel and the newest message printed from it.
+struct Followed {
+    label: String,
+    latest: u64,
+}
+
+/// Follows channels until interrupted, printing new messages that pass the
+/// filter as text lines or JSON lines. `channels` pairs ids with the label
+/// prefixed to text lines, empty when following a single channel.
+pub async fn follow(
+    token: String,
+    channels: Vec<(String, String)>,
+    filter: Filter,
+    source: Source,
+    json: bool,
+) -> Result<(), ApiError> {
+    let mut tail = Tail {
+        channels: HashMap::new(),
+        filter,
+        json,
+    };
+
+    // Start from the newest message so only what arrives from now on shows
+    for (channel_id, label) in channels {
+        let messages = api::fetch_messages(token.clone(), channel_id.clone()).await?;
+        let latest = messages.last().map_or(0, |m| m.snowflake());
+        tail.channels.insert(channel_id, Followed { label, latest });
+    }
+
+    match source {
+        Source::Gateway => {
+            let (sender, mut events) = mpsc::channel(100);
+            tokio::spawn(gateway::listen(token.clone(), sender));
+
+            while let Some(event) = events.next().await {
+                match event {
+                    // Whatever was sent while we were (re)connecting
+                    Event::Connected(_) => tail.catch_up(&token).await,
+                    Event::MessageCreate(message) => tail.print(&message),
+                    _ => {}
+                }
+            }
+
+            Err(ApiError::Other("Gateway stream ended".to_string()))
+        }
+        Source::Poll(interval) => loop {
+            tail.catch_up(&token).await;
+            tokio::time::sleep(interval).await;
+        },
+    }
+}
+
+struct Tail {
+    channels: HashMap<String, Followed>,
+    filter: Filter,
+    json: bool,
+}
+
+impl Tail {
+    /// Pages `after=` the newest printed message of every channel until
+    /// each is up to date. Failures are reported and retried next time.
+    async fn catch_up(&mut self, token: &str) {
+        let channel_ids: Vec<String> = self.channels.keys().cloned().collect();
+
+        for channel_id in channel_ids {
+            loop {
+                let after = self.channels[&channel_id].latest.to_string();
+                let page =
+                    match api::fetch_messages_after(token.to_string(), channel_id.clone(), after)
+                        .await
+                    {
+                        Ok(page) => page,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            break;
+                        }
+                    };
+
+                for message in &page {
+                    self.print(message);
+                }
+                if page.len() < api::MAX_MESSAGE_PAGE {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Prints a message from a followed channel once, if it passes the filter.
+    fn print(&mut self, message: &Message) {
+        let Some(followed) = self.channels.get_mut(&message.channel_id) else {
+            return;
+        };
+        // Already seen through the gateway or a catch-up page
+        if message.snowflake() <= followed.latest {
+            return;
+        }
+        followed.latest = message.snowflake();
+
+        if !self.filter.matches(message) {
+            return;
+        }
+
+        if self.json {
+            match serde_json::to_string(message) {
+                Ok(line) => write_line(line),
+                Err(e) => eprintln!("Failed to serialize message: {}", e),
+            }
+        } else {
+            if followed.label.is_empty() {
+                write_line(export::text_line(message));
+            } else {
+                write_line(format_args!(
+                    "{} {}",
+                    followed.label,
+                    export::text_line(message)
+                ));
+            }
+            for attachment in &message.attachments {
+                write_line(format_args!("    [attachment] {}", attachment.url));
+            }
+        }
+    }
+}
@@ -0,0 +1,792 @@
+use crate::api;
+use crate::app::{App, Effect};
+use crate::state::{AppState, ChatRow};
+use crate::{subscriptions, ui, Message};
+use iced::futures::channel::mpsc;
+use iced_futures::Runtime;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::cell::Cell;
+use std::time::Duration;
+
+// How long to wait for a key before handling app messages again
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Lines moved by Page Up / Page Down in the message pane
+const PAGE_LINES: usize = 10;
+// Debug output goes here while the interface owns the terminal
+#[cfg(unix)]
+const LOG_FILE: &str = "tui.log";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pane {
+    Guilds,
+    Channels,
+    Messages,
+    Composer,
+}
+
+impl Pane {
+    const ALL: [Pane; 4] = [Pane::Guilds, Pane::Channels, Pane::Messages, Pane::Composer];
+
+    fn cycle(self, forward: bool) -> Pane {
+        let index = Pane::ALL.iter().position(|p| *p == self).unwrap_or(0);
+        let next = if forward {
+            (index + 1) % Pane::ALL.len()
+        } else {
+            (index + Pane::ALL.len() - 1) % Pane::ALL.len()
+        };
+        Pane::ALL[next]
+    }
+}
+
+/// A line of the guild or channel pane. Rows without a message, like
+/// category headers and voice channels, are skipped by the cursor.
+struct Row {
+    label: String,
+    message: Option<Message>,
+    selected: bool,
+}
+
+/// The terminal frontend: the shared `App` with its effects and the GUI's
+/// subscriptions run on iced's own runtime, keys mapped to its messages and
+/// its `AppState` drawn with ratatui.
+struct Tui {
+    app: App,
+    runtime: Runtime<iced::executor::Default, mpsc::Sender<Message>, Message>,
+    focus: Pane,
+    guild_cursor: usize,
+    channel_cursor: usize,
+    // Lines scrolled up from the newest message, and the most the last
+    // draw allowed
+    scroll: usize,
+    max_scroll: Cell<usize>,
+    quit: bool,
+}
+
+/// Runs the terminal interface until the user quits, logging in right away
+/// when a token is configured. Returns the process exit code.
+pub fn run(token: Option<String>) -> i32 {
+    let executor = match iced::executor::Default::new() {
+        Ok(executor) => executor,
+        Err(e) => {
+            eprintln!("Failed to start runtime: {}", e);
+            return 1;
+        }
+    };
+    let (sender, mut receiver) = mpsc::channel(100);
+    let runtime = Runtime::new(executor, sender);
+    let app = runtime.enter(App::new);
+
+    let stderr = redirect_stderr();
+
+    let mut tui = Tui {
+        app,
+        runtime,
+        focus: Pane::Guilds,
+        guild_cursor: 0,
+        channel_cursor: 0,
+        scroll: 0,
+        max_scroll: Cell::new(0),
+        quit: false,
+    };
+    if let Some(token) = token {
+        tui.update(Message::TokenInputChanged(token));
+        tui.update(Message::Login);
+    }
+
+    let result = ratatui::try_init().and_then(|mut terminal| {
+        let result = tui.event_loop(&mut terminal, &mut receiver);
+        ratatui::restore();
+        result
+    });
+
+    restore_stderr(stderr);
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Terminal error: {}", e);
+            1
+        }
+    }
+}
+
+impl Tui {
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        receiver: &mut mpsc::Receiver<Message>,
+    ) -> std::io::Result<()> {
+        let mut dirty = true;
+
+        while !self.quit {
+            if dirty {
+                terminal.draw(|frame| self.draw(frame))?;
+                dirty = false;
+            }
+
+            // Resizes and other events only need a redraw
+            if event::poll(POLL_INTERVAL)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.handle_key(key);
+                    }
+                }
+                dirty = true;
+            }
+
+            while let Ok(message) = receiver.try_recv() {
+                self.update(message);
+                dirty = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, message: Message) {
+        let effect = self.app.update(message);
+        self.perform(effect);
+        let subscription = subscriptions::session(&self.app.state);
+        self.runtime.track(subscription.into_recipes());
+    }
+
+    fn perform(&mut self, effect: Effect) {
+        for future in effect.into_futures() {
+            self.runtime.spawn(future);
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.app.state
+    }
+
+    // Keys
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        if !self.state().logged_in {
+            self.login_key(key, ctrl);
+            return;
+        }
+
+        match key.code {
+            KeyCode::Tab => self.focus = self.focus.cycle(true),
+            KeyCode::BackTab => self.focus = self.focus.cycle(false),
+            _ if self.focus == Pane::Composer => self.composer_key(key),
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('i') => self.focus = Pane::Composer,
+            KeyCode::Char('L') if self.state().offline => self.update(Message::ShowLogin),
+            KeyCode::Left | KeyCode::Char('h') => self.focus = self.focus.cycle(false),
+            _ if self.focus == Pane::Messages => self.messages_key(key),
+            _ => self.list_key(key),
+        }
+    }
+
+    fn login_key(&mut self, key: KeyEvent, ctrl: bool) {
+        let mut token = self.state().token_input.clone();
+
+        match key.code {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Enter if !token.is_empty() => self.update(Message::Login),
+            KeyCode::Char('o') if ctrl => self.update(Message::BrowseOffline),
+            KeyCode::Char(c) if !ctrl => {
+                token.push(c);
+                self.update(Message::TokenInputChanged(token));
+            }
+            KeyCode::Backspace => {
+                token.pop();
+                self.update(Message::TokenInputChanged(token));
+            }
+            _ => {}
+        }
+    }
+
+    fn list_key(&mut self, key: KeyEvent) {
+        let guilds = self.focus == Pane::Guilds;
+        let rows = if guilds {
+            guild_rows(self.state())
+        } else {
+            channel_rows(self.state())
+        };
+        let cursor = if guilds {
+            &mut self.guild_cursor
+        } else {
+            &mut self.channel_cursor
+        };
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => *cursor = step(&rows, *cursor, false),
+            KeyCode::Down | KeyCode::Char('j') => *cursor = step(&rows, *cursor, true),
+            KeyCode::Char('v') if !guilds => self.update(Message::ToggleLockedChannels),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                let Some(message) = rows.get(*cursor).and_then(|row| row.message.clone()) else {
+                    return;
+                };
+                if guilds {
+                    self.channel_cursor = 0;
+                    self.focus = Pane::Channels;
+                } else {
+                    self.focus = Pane::Messages;
+                }
+                self.scroll = 0;
+                self.update(message);
+            }
+            _ => {}
+        }
+    }
+
+    fn messages_key(&mut self, key: KeyEvent) {
+        let max_scroll = self.max_scroll.get();
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = (self.scroll + 1).min(max_scroll),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageUp => self.scroll = (self.scroll + PAGE_LINES).min(max_scroll),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(PAGE_LINES),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = max_scroll,
+            KeyCode::End | KeyCode::Char('G') => self.scroll = 0,
+            KeyCode::Char('b') if self.state().visible_gap().is_some() => {
+                self.update(Message::BackfillGap)
+            }
+            KeyCode::Char('x') => self.toggle_blocked(),
+            _ => {}
+        }
+    }
+
+    /// Shows every collapsed run of blocked messages, or hides them all
+    /// again when none is collapsed.
+    fn toggle_blocked(&mut self) {
+        let state = self.state();
+        let runs: Vec<(Vec<String>, bool)> = state
+            .chat_rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                ChatRow::Blocked(run) => Some((
+                    run.iter().map(|m| m.id.clone()).collect(),
+                    state.is_revealed(run),
+                )),
+                ChatRow::Message(_) => None,
+            })
+            .collect();
+        let reveal = runs.iter().any(|(_, revealed)| !revealed);
+
+        for (ids, revealed) in runs {
+            if revealed != reveal {
+                self.update(Message::ToggleBlockedMessages(ids));
+            }
+        }
+    }
+
+    fn composer_key(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Esc {
+            self.focus = Pane::Messages;
+            return;
+        }
+        if !can_compose(self.state()) {
+            return;
+        }
+
+        let mut input = self.state().message_input.clone();
+        match key.code {
+            KeyCode::Enter if !input.trim().is_empty() => {
+                self.scroll = 0;
+                self.update(Message::SendMessage);
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                self.update(Message::MessageInputChanged(input));
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                self.update(Message::MessageInputChanged(input));
+            }
+            _ => {}
+        }
+    }
+
+    // Drawing
+
+    fn draw(&self, frame: &mut Frame) {
+        let state = self.state();
+        if !state.logged_in {
+            draw_login(frame, state);
+            return;
+        }
+
+        let [body, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [guilds, channels, chat] = Layout::horizontal([
+            Constraint::Length(24),
+            Constraint::Length(30),
+            Constraint::Min(20),
+        ])
+        .areas(body);
+        let [messages, composer] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(3)]).areas(chat);
+
+        let channels_title = if state.viewing_dms {
+            "Direct Messages"
+        } else {
+            state
+                .selected_guild
+                .as_deref()
+                .and_then(|id| state.guild_name(id))
+                .unwrap_or("Channels")
+        };
+
+        self.draw_list(
+            frame,
+            guilds,
+            "Servers",
+            guild_rows(state),
+            self.guild_cursor,
+            Pane::Guilds,
+        );
+        self.draw_list(
+            frame,
+            channels,
+            channels_title,
+            channel_rows(state),
+            self.channel_cursor,
+            Pane::Channels,
+        );
+        self.draw_messages(frame, messages);
+        self.draw_composer(frame, composer);
+        draw_status(frame, status, state);
+    }
+
+    fn block<'a>(&self, title: &'a str, pane: Pane) -> Block<'a> {
+        let style = if self.focus == pane {
+            Style::new().fg(Color::Cyan)
+        } else {
+            Style::new().fg(Color::DarkGray)
+        };
+        Block::bordered().title(title).border_style(style)
+    }
+
+    fn draw_list(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        rows: Vec<Row>,
+        cursor: usize,
+        pane: Pane,
+    ) {
+        let items: Vec<ListItem> = rows
+            .into_iter()
+            .map(|row| {
+                let style = if row.selected {
+                    Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+                } else if row.message.is_none() {
+                    Style::new().fg(Color::DarkGray)
+                } else {
+                    Style::new()
+                };
+                ListItem::new(Line::styled(row.label, style))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(self.block(title, pane))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut list_state = ListState::default();
+        if self.focus == pane {
+            list_state.select(Some(cursor));
+        }
+
+        frame.render_stateful_widget(list, area, &mut list_state);
+    }
+
+    fn draw_messages(&self, frame: &mut Frame, area: Rect) {
+        let state = self.state();
+        let title = match state.selected_channel_info() {
+            Some(channel) if channel.guild_id.is_some() => {
+                format!("#{}", state.channel_display_name(channel))
+            }
+            Some(channel) => format!("@{}", state.channel_display_name(channel)),
+            None => String::from("Messages"),
+        };
+        let block = self.block(&title, Pane::Messages);
+
+        if state.selected_channel.is_none() {
+            let hint = Paragraph::new("Select a channel").block(block);
+            frame.render_widget(hint, area);
+            return;
+        }
+
+        let width = area.width.saturating_sub(4).max(1) as usize;
+        let lines = message_lines(state, width);
+
+        // Bottom-anchored, scrolled up by whole lines
+        let height = area.height.saturating_sub(2) as usize;
+        let max_scroll = lines.len().saturating_sub(height);
+        self.max_scroll.set(max_scroll);
+        let scroll = self.scroll.min(max_scroll);
+        let end = lines.len() - scroll;
+        let start = end.saturating_sub(height);
+
+        let paragraph = Paragraph::new(lines[start..end].to_vec()).block(block);
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_composer(&self, frame: &mut Frame, area: Rect) {
+        let state = self.state();
+        let block = self.block("Message", Pane::Composer);
+
+        let line = if can_compose(state) {
+            let cursor = if self.focus == Pane::Composer {
+                "█"
+            } else {
+                ""
+            };
+            Line::from(format!("{}{}", state.message_input, cursor))
+        } else {
+            Line::styled("Read-only", Style::new().fg(Color::DarkGray))
+        };
+
+        // Keep the end of long drafts in view
+        let overflow = (line.width() as u16).saturating_sub(area.width.saturating_sub(2));
+        let composer = Paragraph::new(line).block(block).scroll((0, overflow));
+        frame.render_widget(composer, area);
+    }
+}
+
+fn draw_login(frame: &mut Frame, state: &AppState) {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(9),
+        Constraint::Fill(1),
+    ])
+    .areas(frame.area());
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(60),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+
+    let hint = Style::new().fg(Color::DarkGray);
+    let mut lines = vec![
+        Line::from(format!("Token: {}█", "*".repeat(state.token_input.len()))),
+        Line::default(),
+        Line::styled("Enter: log in   Esc: quit", hint),
+    ];
+    if let Some(account) = &state.cached_account {
+        lines.push(Line::styled(
+            format!("Ctrl+O: browse offline as @{}", account.username),
+            hint,
+        ));
+    }
+    if let Some(error) = &state.error {
+        lines.push(Line::default());
+        lines.push(Line::styled(error.clone(), Style::new().fg(Color::Red)));
+    }
+
+    let login = Paragraph::new(lines)
+        .block(Block::bordered().title("Discord Lite"))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(login, area);
+}
+
+fn draw_status(frame: &mut Frame, area: Rect, state: &AppState) {
+    let mut spans = Vec::new();
+    if let Some(user) = &state.current_user {
+        let [r, g, b] = state.current_status.color();
+        spans.push(Span::styled("● ", Style::new().fg(rgb([r, g, b]))));
+        spans.push(Span::raw(format!("@{}  ", user.username)));
+    }
+    if state.offline {
+        spans.push(Span::styled(
+            "OFFLINE (L: log in)  ",
+            Style::new().fg(Color::Yellow),
+        ));
+    }
+
+    if let Some(error) = &state.error {
+        spans.push(Span::styled(error.clone(), Style::new().fg(Color::Red)));
+    } else {
+        spans.push(Span::styled(
+            "Tab: pane  ↑↓: move  Enter: open  i: write  b: older  q: quit",
+            Style::new().fg(Color::DarkGray),
+        ));
+    }
+
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn guild_rows(state: &AppState) -> Vec<Row> {
+    let mut rows = vec![Row {
+        label: String::from("@ Direct Messages"),
+        message: Some(Message::SelectDirectMessages),
+        selected: state.viewing_dms,
+    }];
+
+    for guild in &state.guilds {
+        rows.push(Row {
+            label: guild.name.clone(),
+            message: Some(Message::SelectGuild(guild.id.clone())),
+            selected: state.selected_guild.as_ref() == Some(&guild.id),
+        });
+    }
+    for archive in &state.archives {
+        rows.push(Row {
+            label: format!("📁 {}", archive.name),
+            message: Some(Message::SelectGuild(archive.id.clone())),
+            selected: state.selected_guild.as_ref() == Some(&archive.id),
+        });
+    }
+
+    rows
+}
+
+/// DMs, or the selected guild's channels in the same order as the GUI:
+/// top-level channels, then each category with its channels.
+fn channel_rows(state: &AppState) -> Vec<Row> {
+    let channel_row = |channel: &crate::api::Channel, indent: &str| {
+        let glyph = match channel.channel_type {
+            1 | 3 => "@",
+            2 => "🔊",
+            13 => "🎙",
+            15 => "💬",
+            5 => "📢",
+            _ => "#",
+        };
+        let lock = if state.can_view(channel) { "" } else { " 🔒" };
+        // Only text-based channels open in the message pane
+        let opens = matches!(channel.channel_type, 0 | 1 | 3 | 5);
+
+        Row {
+            label: format!(
+                "{}{} {}{}",
+                indent,
+                glyph,
+                state.channel_display_name(channel),
+                lock
+            ),
+            message: opens.then(|| Message::SelectChannel(channel.id.clone())),
+            selected: state.selected_channel.as_ref() == Some(&channel.id),
+        }
+    };
+
+    if state.viewing_dms {
+        return state
+            .sorted_dm_channels()
+            .into_iter()
+            .map(|channel| channel_row(channel, ""))
+            .collect();
+    }
+
+    let visible: Vec<_> = state
+        .channels
+        .iter()
+        .filter(|c| c.channel_type != 4)
+        .filter(|c| state.show_locked_channels || state.can_view(c))
+        .collect();
+
+    let mut rows: Vec<Row> = visible
+        .iter()
+        .filter(|c| c.parent_id.is_none())
+        .map(|channel| channel_row(channel, ""))
+        .collect();
+
+    for category in state.channels.iter().filter(|c| c.channel_type == 4) {
+        let mut children: Vec<_> = visible
+            .iter()
+            .filter(|c| c.parent_id.as_ref() == Some(&category.id))
+            .collect();
+        if children.is_empty() {
+            continue;
+        }
+        children.sort_by_key(|c| c.position);
+
+        rows.push(Row {
+            label: category.name.as_deref().unwrap_or("Unknown").to_uppercase(),
+            message: None,
+            selected: false,
+        });
+        rows.extend(
+            children
+                .into_iter()
+                .map(|channel| channel_row(channel, "  ")),
+        );
+    }
+
+    rows
+}
+
+/// The selected channel's messages as wrapped lines, oldest first.
+fn message_lines(state: &AppState, width: usize) -> Vec<Line<'static>> {
+    let dim = Style::new().fg(Color::DarkGray);
+    let gap_before = state.visible_gap().map(|gap| gap.before);
+    let is_gap = |message: &api::Message| gap_before == Some(message.snowflake());
+    let gap_line = || Line::styled("── older messages missing, b: load ──", dim);
+    let mut lines = Vec::new();
+
+    // Runs of consecutive messages from blocked users are collapsed
+    for row in state.chat_rows() {
+        match row {
+            ChatRow::Message(message) => {
+                if is_gap(message) {
+                    lines.push(gap_line());
+                }
+                push_message(&mut lines, state, message, width);
+            }
+            ChatRow::Blocked(run) => {
+                // A collapsed run still shows a gap hidden inside it
+                let revealed = state.is_revealed(run);
+                let gap_above = if revealed {
+                    is_gap(&run[0])
+                } else {
+                    run.iter().any(is_gap)
+                };
+                if gap_above {
+                    lines.push(gap_line());
+                }
+
+                let label = if run.len() == 1 {
+                    "1 blocked message".to_string()
+                } else {
+                    format!("{} blocked messages", run.len())
+                };
+                let action = if revealed { "hide" } else { "show" };
+                lines.push(Line::styled(format!("── {}, x: {} ──", label, action), dim));
+
+                if revealed {
+                    for (i, message) in run.iter().enumerate() {
+                        if i > 0 && is_gap(message) {
+                            lines.push(gap_line());
+                        }
+                        push_message(&mut lines, state, message, width);
+                    }
+                }
+            }
+        }
+    }
+
+    lines
+}
+
+/// A message as its author line followed by the wrapped content.
+fn push_message(
+    lines: &mut Vec<Line<'static>>,
+    state: &AppState,
+    message: &api::Message,
+    width: usize,
+) {
+    let dim = Style::new().fg(Color::DarkGray);
+    let (name, color) = state.author_display(message);
+    let name_style = Style::new()
+        .fg(color.map_or(Color::LightBlue, rgb))
+        .add_modifier(Modifier::BOLD);
+    lines.push(Line::from(vec![
+        Span::styled(name, name_style),
+        Span::styled(ui::format_timestamp(&message.timestamp), dim),
+    ]));
+
+    let content = ui::plain_markdown(state, &message.content);
+    for line in content.lines() {
+        for chunk in wrap(line, width) {
+            lines.push(Line::from(format!("  {}", chunk)));
+        }
+    }
+    for attachment in &message.attachments {
+        lines.push(Line::styled(
+            format!("  [attachment] {}", attachment.url),
+            dim,
+        ));
+    }
+}
+
+/// Splits a line into chunks of at most `width` characters.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    if line.is_empty() {
+        return vec![String::new()];
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    chars
+        .chunks(width)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// The next row in the given direction that can be opened, or the cursor
+/// itself at either end.
+fn step(rows: &[Row], cursor: usize, forward: bool) -> usize {
+    let candidates: Box<dyn Iterator<Item = usize>> = if forward {
+        Box::new(cursor + 1..rows.len())
+    } else {
+        Box::new((0..cursor.min(rows.len())).rev())
+    };
+
+    candidates
+        .into_iter()
+        .find(|&i| rows[i].message.is_some())
+        .unwrap_or(cursor)
+}
+
+fn can_compose(state: &AppState) -> bool {
+    !state.offline
+        && !state.viewing_archive()
+        && state
+            .selected_channel_info()
+            .is_some_and(|channel| state.can_send_in(channel))
+}
+
+fn rgb([r, g, b]: [f32; 3]) -> Color {
+    Color::Rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+}
+
+/// Debug output would scribble over the interface, so stderr goes to a log
+/// file in the data directory until it's restored. Returns the original.
+#[cfg(unix)]
+fn redirect_stderr() -> Option<i32> {
+    use std::os::fd::AsRawFd;
+
+    let path = crate::store::data_dir().ok()?.join(LOG_FILE);
+    std::fs::create_dir_all(path.parent()?).ok()?;
+    let log = std::fs::File::create(&path).ok()?;
+
+    // SAFETY: plain descriptor calls on stderr and a file we own
+    unsafe {
+        let original = libc::dup(libc::STDERR_FILENO);
+        if original < 0 {
+            return None;
+        }
+        if libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO) < 0 {
+            libc::close(original);
+            return None;
+        }
+        Some(original)
+    }
+}
+
+#[cfg(unix)]
+fn restore_stderr(original: Option<i32>) {
+    if let Some(original) = original {
+        // SAFETY: `original` came from `dup` in `redirect_stderr`
+        unsafe {
+            libc::dup2(original, libc::STDERR_FILENO);
+            libc::close(original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn redirect_stderr() -> Option<i32> {
+    None
+}
+
+#[cfg(not(unix))]
+fn restore_stderr(_original: Option<i32>) {}
@@ -1,10 +1,26 @@
-use crate::api::Message;
-use crate::state::{AppState, UserStatus};
+use crate::api::{Channel, Message};
+use crate::api::{
+    Relationship, VoiceState, MESSAGE_FLAG_CROSSPOSTED, NOTIFY_ALL_MESSAGES, NOTIFY_INHERIT,
+    NOTIFY_NOTHING, NOTIFY_ONLY_MENTIONS, RELATIONSHIP_BLOCKED, RELATIONSHIP_FRIEND,
+    RELATIONSHIP_INCOMING_REQUEST, RELATIONSHIP_OUTGOING_REQUEST,
+};
+use crate::export::ExportFormat;
+use crate::gateway::{ListMember, MemberListItem};
+use crate::permissions;
+use crate::search::SEARCH_PAGE_SIZE;
+use crate::state::{AppState, ChatRow, ForumSort, FriendsTab, HeaderPopover, UserStatus};
 use crate::Message as AppMessage;
-use iced::widget::{button, column, container, row, scrollable, text, text_input, Column};
+use iced::widget::{
+    button, column, container, image, row, scrollable, text, text_input, tooltip, Column,
+};
 use iced::{Element, Length};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-pub fn view(state: &AppState) -> Element<AppMessage> {
+/// Image handles of the DM avatars, keyed like [`AppState::avatars`].
+pub type Avatars = HashMap<String, image::Handle>;
+
+pub fn view<'a>(state: &'a AppState, avatars: &'a Avatars) -> Element<'a, AppMessage> {
     if !state.logged_in {
         return login_view(state);
     }
@@ -19,10 +35,10 @@ pub fn view(state: &AppState) -> Element<AppMessage> {
         return user_profile_modal(state);
     }
 
-    main_view(state)
+    main_view(state, avatars)
 }
 
-fn login_view(state: &AppState) -> Element<AppMessage> {
+fn login_view(state: &AppState) -> Element<'_, AppMessage> {
     let mut content = column![
         text("Discord Lite").size(32),
         text("Login with your Discord token").size(16),
@@ -36,6 +52,30 @@ fn login_view(state: &AppState) -> Element<AppMessage> {
     .spacing(20)
     .padding(40);
 
+    if let Some(account) = &state.cached_account {
+        content = content.push(
+            button(text(format!("Browse offline as @{}", account.username)).size(14))
+                .on_press(AppMessage::BrowseOffline)
+                .padding(8)
+                .style(iced::theme::Button::Secondary),
+        );
+    }
+
+    content = content.push(
+        row![
+            text_input("Export folder to open", &state.archive_path_input)
+                .on_input(AppMessage::ArchivePathInputChanged)
+                .on_submit(AppMessage::OpenArchive)
+                .padding(8)
+                .width(300),
+            button(text("Open archive").size(14))
+                .on_press(AppMessage::OpenArchive)
+                .padding(8)
+                .style(iced::theme::Button::Secondary),
+        ]
+        .spacing(10),
+    );
+
     if let Some(error) = &state.error {
         content = content.push(text(error).style(iced::Color::from_rgb(1.0, 0.3, 0.3)));
     }
@@ -48,23 +88,68 @@ fn login_view(state: &AppState) -> Element<AppMessage> {
         .into()
 }
 
-fn main_view(state: &AppState) -> Element<AppMessage> {
+fn main_view<'a>(state: &'a AppState, avatars: &'a Avatars) -> Element<'a, AppMessage> {
     let left_panel = guild_list(state);
-    let middle_panel = channel_list(state);
-    let right_panel = chat_view(state);
+    let middle_panel = channel_list(state, avatars);
+    let right_panel = if state.show_mentions {
+        mentions_view(state)
+    } else if state.show_friends {
+        friends_view(state)
+    } else if state.open_thread.is_some() || member_list_visible(state) || state.show_search {
+        let mut panels = row![chat_view(state)]
+            .width(Length::Fill)
+            .height(Length::Fill);
+        if member_list_visible(state) {
+            panels = panels.push(member_list_view(state));
+        }
+        if state.open_thread.is_some() {
+            panels = panels.push(thread_panel(state));
+        }
+        if state.show_search {
+            panels = panels.push(search_panel(state));
+        }
+        panels.into()
+    } else {
+        chat_view(state)
+    };
 
-    let content = row![left_panel, middle_panel, right_panel]
+    let panels = row![left_panel, middle_panel, right_panel]
         .spacing(0)
         .width(Length::Fill)
         .height(Length::Fill);
 
+    let mut content = Column::new();
+    if state.offline {
+        content = content.push(offline_banner());
+    }
+    let content = content.push(panels);
+
     container(content)
         .width(Length::Fill)
         .height(Length::Fill)
         .into()
 }
 
-fn guild_list(state: &AppState) -> Element<AppMessage> {
+fn offline_banner<'a>() -> Element<'a, AppMessage> {
+    container(
+        row![
+            text("Offline: showing cached data, read-only")
+                .size(13)
+                .style(iced::Color::from_rgb(1.0, 0.8, 0.4)),
+            iced::widget::horizontal_space(),
+            button(text("Log in").size(12))
+                .on_press(AppMessage::ShowLogin)
+                .padding(4),
+        ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center),
+    )
+    .padding([6, 10])
+    .width(Length::Fill)
+    .into()
+}
+
+fn guild_list(state: &AppState) -> Element<'_, AppMessage> {
     let mut header_column = Column::new().spacing(5).padding(10).width(200);
 
     if let Some(user) = &state.current_user {
@@ -140,9 +225,38 @@ fn guild_list(state: &AppState) -> Element<AppMessage> {
             );
         }
 
+        let mentions_label = if state.mentions.is_empty() {
+            "@ Mentions".to_string()
+        } else {
+            format!("@ Mentions ({})", state.mentions.len())
+        };
+        header_column = header_column.push(
+            button(text(mentions_label).size(12))
+                .on_press(AppMessage::ToggleMentions)
+                .padding(4)
+                .width(Length::Fill)
+                .style(if state.show_mentions {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+
         header_column = header_column.push(text("-----------").size(12));
     }
 
+    header_column = header_column.push(
+        button(text("Direct Messages").size(14))
+            .on_press(AppMessage::SelectDirectMessages)
+            .padding(8)
+            .width(Length::Fill)
+            .style(if state.viewing_dms {
+                iced::theme::Button::Primary
+            } else {
+                iced::theme::Button::Secondary
+            }),
+    );
+
     header_column = header_column.push(text("Servers").size(18));
 
     // Create scrollable guild list
@@ -175,6 +289,29 @@ fn guild_list(state: &AppState) -> Element<AppMessage> {
         guild_column = guild_column.push(btn);
     }
 
+    // Opened exports, read-only
+    for archive in &state.archives {
+        let is_selected = state.selected_guild.as_ref() == Some(&archive.id);
+        guild_column = guild_column.push(
+            button(text(format!("📁 {}", archive.name)).size(14))
+                .on_press(AppMessage::SelectGuild(archive.id.clone()))
+                .padding(8)
+                .width(Length::Fill)
+                .style(if is_selected {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+    }
+    guild_column = guild_column.push(
+        text_input("Open archive...", &state.archive_path_input)
+            .on_input(AppMessage::ArchivePathInputChanged)
+            .on_submit(AppMessage::OpenArchive)
+            .padding(6)
+            .size(12),
+    );
+
     let guild_scroll = scrollable(guild_column)
         .height(Length::Fill)
         .width(Length::Fill);
@@ -188,20 +325,35 @@ fn guild_list(state: &AppState) -> Element<AppMessage> {
         .into()
 }
 
-fn channel_list(state: &AppState) -> Element<AppMessage> {
+fn channel_list<'a>(state: &'a AppState, avatars: &'a Avatars) -> Element<'a, AppMessage> {
+    if state.viewing_dms {
+        return dm_list(state, avatars);
+    }
+
     let mut header_column = Column::new().spacing(5).padding(10).width(200);
 
-    if state.selected_guild.is_some() {
-        let guild_name = state
-            .guilds
-            .iter()
-            .find(|g| Some(&g.id) == state.selected_guild.as_ref())
-            .map(|g| g.name.as_str())
-            .unwrap_or("Unknown");
+    if let Some(guild_id) = &state.selected_guild {
+        let guild_name = state.guild_name(guild_id).unwrap_or("Unknown");
 
         header_column = header_column.push(text(guild_name).size(16));
         header_column = header_column.push(text("-----------").size(12));
-        header_column = header_column.push(text("Channels").size(14));
+        header_column = header_column.push(
+            row![
+                text("Channels").size(14).width(Length::Fill),
+                button(
+                    text(if state.show_locked_channels {
+                        "Hide locked"
+                    } else {
+                        "Show locked"
+                    })
+                    .size(11),
+                )
+                .on_press(AppMessage::ToggleLockedChannels)
+                .padding(2)
+                .style(iced::theme::Button::Text),
+            ]
+            .align_items(iced::Alignment::Center),
+        );
 
         let mut channel_column = Column::new().spacing(3).width(Length::Fill);
 
@@ -212,28 +364,48 @@ fn channel_list(state: &AppState) -> Element<AppMessage> {
             .filter(|c| c.channel_type == 4)
             .collect();
 
+        // Channels we cannot view are hidden unless asked for
         let channels_with_parents: Vec<_> = state
             .channels
             .iter()
-            .filter(|c| (c.channel_type == 0 || c.channel_type == 2) && c.parent_id.is_some())
+            .filter(|c| c.channel_type != 4 && c.parent_id.is_some())
+            .filter(|c| state.show_locked_channels || state.can_view(c))
             .collect();
 
         let channels_without_parents: Vec<_> = state
             .channels
             .iter()
-            .filter(|c| (c.channel_type == 0 || c.channel_type == 2) && c.parent_id.is_none())
+            .filter(|c| c.channel_type != 4 && c.parent_id.is_none())
+            .filter(|c| state.show_locked_channels || state.can_view(c))
             .collect();
 
         // First, display channels without parents (top-level channels)
         for channel in &channels_without_parents {
-            channel_column =
-                channel_column.push(render_channel(channel, &state.selected_channel, false));
+            channel_column = channel_column.push(render_channel(
+                channel,
+                &state.selected_channel,
+                false,
+                !state.can_view(channel),
+            ));
+            channel_column = channel_column.push(render_threads(state, channel));
+            channel_column = channel_column.push(render_voice_occupants(state, channel));
         }
 
         // Then, display categories with their children
         for category in &categories {
             let category_name = category.name.as_deref().unwrap_or("Unknown");
 
+            // Display channels in this category, sorted by position
+            let mut category_channels: Vec<_> = channels_with_parents
+                .iter()
+                .filter(|c| c.parent_id.as_ref() == Some(&category.id))
+                .collect();
+
+            // Categories with nothing visible in them are hidden too
+            if category_channels.is_empty() {
+                continue;
+            }
+
             // Display category header
             channel_column = channel_column.push(
                 container(
@@ -244,17 +416,17 @@ fn channel_list(state: &AppState) -> Element<AppMessage> {
                 .padding([8, 8, 2, 8]),
             );
 
-            // Display channels in this category, sorted by position
-            let mut category_channels: Vec<_> = channels_with_parents
-                .iter()
-                .filter(|c| c.parent_id.as_ref() == Some(&category.id))
-                .collect();
-
             category_channels.sort_by_key(|c| c.position);
 
             for channel in category_channels {
-                channel_column =
-                    channel_column.push(render_channel(channel, &state.selected_channel, true));
+                channel_column = channel_column.push(render_channel(
+                    channel,
+                    &state.selected_channel,
+                    true,
+                    !state.can_view(channel),
+                ));
+                channel_column = channel_column.push(render_threads(state, channel));
+                channel_column = channel_column.push(render_voice_occupants(state, channel));
             }
         }
 
@@ -274,7 +446,235 @@ fn channel_list(state: &AppState) -> Element<AppMessage> {
         .into()
 }
 
-fn chat_view(state: &AppState) -> Element<AppMessage> {
+fn dm_list<'a>(state: &'a AppState, avatars: &'a Avatars) -> Element<'a, AppMessage> {
+    let mut header_column = Column::new().spacing(5).padding(10).width(200);
+
+    header_column = header_column.push(
+        button(text("Friends").size(14))
+            .on_press(AppMessage::ShowFriends)
+            .padding(8)
+            .width(Length::Fill)
+            .style(if state.show_friends {
+                iced::theme::Button::Primary
+            } else {
+                iced::theme::Button::Secondary
+            }),
+    );
+    header_column = header_column.push(text("-----------").size(12));
+    header_column = header_column.push(text("Direct Messages").size(16));
+
+    let mut dm_column = Column::new().spacing(3).width(Length::Fill);
+
+    for channel in state.sorted_dm_channels() {
+        let is_selected = state.selected_channel.as_ref() == Some(&channel.id);
+
+        // Group DM icons are keyed by channel, 1:1 DMs by the recipient
+        let icon_key = if channel.channel_type == 3 {
+            Some(&channel.id)
+        } else {
+            channel.recipients.first().map(|u| &u.id)
+        };
+
+        let icon: Element<AppMessage> = match icon_key.and_then(|key| avatars.get(key)) {
+            Some(handle) => image(handle.clone()).width(24).height(24).into(),
+            None => text(if channel.channel_type == 3 {
+                "👥"
+            } else {
+                "@"
+            })
+            .size(16)
+            .width(24)
+            .into(),
+        };
+
+        let btn = button(
+            row![icon, text(state.channel_display_name(channel)).size(14)]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+        )
+        .on_press(AppMessage::SelectChannel(channel.id.clone()))
+        .padding(6)
+        .width(Length::Fill)
+        .style(if is_selected {
+            iced::theme::Button::Primary
+        } else {
+            iced::theme::Button::Secondary
+        });
+
+        dm_column = dm_column.push(btn);
+    }
+
+    header_column = header_column.push(
+        scrollable(dm_column)
+            .height(Length::Fill)
+            .width(Length::Fill),
+    );
+
+    container(header_column)
+        .width(200)
+        .height(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn friends_view(state: &AppState) -> Element<'_, AppMessage> {
+    let mut content = Column::new().spacing(10).padding(10).width(Length::Fill);
+
+    // Tabs
+    let mut tabs = row![text("Friends").size(20)]
+        .spacing(8)
+        .padding(10)
+        .align_items(iced::Alignment::Center);
+
+    for tab in [
+        FriendsTab::Online,
+        FriendsTab::All,
+        FriendsTab::Pending,
+        FriendsTab::Blocked,
+    ] {
+        tabs = tabs.push(
+            button(text(tab.display_name()).size(13))
+                .on_press(AppMessage::SelectFriendsTab(tab))
+                .padding(6)
+                .style(if state.friends_tab == tab {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+    }
+
+    content = content.push(tabs);
+
+    // Add friend
+    content = content.push(
+        row![
+            text_input("Add friend by username", &state.friend_request_input)
+                .on_input(AppMessage::FriendRequestInputChanged)
+                .on_submit(AppMessage::SendFriendRequest)
+                .padding(8)
+                .width(Length::Fill),
+            button(text("Send Friend Request").size(13))
+                .on_press(AppMessage::SendFriendRequest)
+                .padding(8),
+        ]
+        .spacing(10)
+        .padding([0, 10]),
+    );
+
+    let relationships = state.relationships_for_tab(state.friends_tab);
+
+    let mut list = Column::new().spacing(6).padding(10);
+
+    list = list.push(
+        text(format!(
+            "{} — {}",
+            state.friends_tab.display_name().to_uppercase(),
+            relationships.len()
+        ))
+        .size(12)
+        .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+    );
+
+    for relationship in relationships {
+        list = list.push(relationship_row(state, relationship));
+    }
+
+    content = content.push(scrollable(list).height(Length::Fill).width(Length::Fill));
+
+    if let Some(error) = &state.error {
+        content = content
+            .push(container(text(error).style(iced::Color::from_rgb(1.0, 0.3, 0.3))).padding(10));
+    }
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn relationship_row<'a>(
+    state: &'a AppState,
+    relationship: &'a Relationship,
+) -> Element<'a, AppMessage> {
+    let (name, username) = match &relationship.user {
+        Some(user) => (
+            user.global_name.as_deref().unwrap_or(&user.username),
+            user.username.as_str(),
+        ),
+        None => (relationship.id.as_str(), ""),
+    };
+
+    let status = state.presences.get(&relationship.id);
+    let color = status.unwrap_or(&UserStatus::Invisible).color();
+    let status_label = match relationship.relationship_type {
+        RELATIONSHIP_INCOMING_REQUEST => "Incoming Friend Request",
+        RELATIONSHIP_OUTGOING_REQUEST => "Outgoing Friend Request",
+        RELATIONSHIP_BLOCKED => "Blocked",
+        _ => status.map(|s| s.display_name()).unwrap_or("Offline"),
+    };
+
+    let mut user_row = row![
+        text("●")
+            .size(14)
+            .style(iced::Color::from_rgb(color[0], color[1], color[2])),
+        column![
+            text(name).size(14),
+            text(format!("{} · {}", username, status_label))
+                .size(11)
+                .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+        ]
+        .width(Length::Fill),
+    ]
+    .spacing(8)
+    .align_items(iced::Alignment::Center);
+
+    let action = |label, message| {
+        button(text(label).size(12))
+            .on_press(message)
+            .padding(4)
+            .style(iced::theme::Button::Secondary)
+    };
+
+    let id = relationship.id.clone();
+    user_row = match relationship.relationship_type {
+        RELATIONSHIP_FRIEND => user_row
+            .push(action("Message", AppMessage::OpenDirectMessage(id.clone())))
+            .push(action("Remove", AppMessage::RemoveRelationship(id.clone())))
+            .push(action("Block", AppMessage::BlockUser(id))),
+        RELATIONSHIP_INCOMING_REQUEST => user_row
+            .push(action(
+                "Accept",
+                AppMessage::AcceptFriendRequest(id.clone()),
+            ))
+            .push(action("Decline", AppMessage::RemoveRelationship(id))),
+        RELATIONSHIP_OUTGOING_REQUEST => {
+            user_row.push(action("Cancel", AppMessage::RemoveRelationship(id)))
+        }
+        RELATIONSHIP_BLOCKED => {
+            user_row.push(action("Unblock", AppMessage::RemoveRelationship(id)))
+        }
+        _ => user_row,
+    };
+
+    container(user_row.padding(6))
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn chat_view(state: &AppState) -> Element<'_, AppMessage> {
+    if let Some(channel) = state.selected_channel_info().filter(|c| !state.can_view(c)) {
+        return no_access_view(state, channel);
+    }
+
+    if let Some(forum) = state
+        .selected_channel_info()
+        .filter(|c| c.channel_type == 15)
+    {
+        return forum_view(state, forum);
+    }
+
     if state.selected_channel.is_none() {
         return container(
             text("Select a channel to view messages")
@@ -288,28 +688,79 @@ fn chat_view(state: &AppState) -> Element<AppMessage> {
         .into();
     }
 
-    let channel_name = state
-        .channels
-        .iter()
-        .find(|c| Some(&c.id) == state.selected_channel.as_ref())
-        .and_then(|c| c.name.as_deref())
-        .unwrap_or("Unknown");
-
     let mut chat_content = Column::new().spacing(10).padding(10).width(Length::Fill);
 
+    let is_announcement = state
+        .selected_channel_info()
+        .map(|c| c.channel_type == 5)
+        .unwrap_or(false);
+
     // Header
-    chat_content = chat_content.push(
-        container(text(format!("# {}", channel_name)).size(20))
-            .padding(10)
-            .width(Length::Fill),
-    );
+    chat_content = chat_content.push(channel_header(state));
+
+    match state.header_popover {
+        Some(HeaderPopover::Pins) => chat_content = chat_content.push(pins_popover(state)),
+        Some(HeaderPopover::Threads) => chat_content = chat_content.push(threads_popover(state)),
+        Some(HeaderPopover::Notifications) => {
+            chat_content = chat_content.push(notifications_popover(state))
+        }
+        Some(HeaderPopover::Export) => chat_content = chat_content.push(export_popover(state)),
+        None => {}
+    }
+
+    if is_announcement && state.follow_picker_open {
+        chat_content = chat_content.push(follow_picker(state));
+    }
 
     // Messages
     let mut messages_column = Column::new().spacing(8).padding(10);
 
-    for message in &state.messages {
-        let msg_view = message_view(message);
-        messages_column = messages_column.push(msg_view);
+    // Gaps sit right above the oldest message after them
+    let gap_before = |message: &Message| {
+        let snowflake = message.snowflake();
+        state.gaps.iter().any(|gap| gap.before == snowflake)
+    };
+
+    // Runs of consecutive messages from blocked users are collapsed
+    for row in state.chat_rows() {
+        match row {
+            ChatRow::Message(message) => {
+                if gap_before(message) {
+                    messages_column = messages_column.push(gap_marker(state));
+                }
+                messages_column = messages_column.push(message_view(
+                    state,
+                    message,
+                    message_actions(state, message),
+                ));
+            }
+            ChatRow::Blocked(run) => {
+                // A collapsed run still shows the gaps hidden inside it
+                let revealed = state.is_revealed(run);
+                let gap_above = if revealed {
+                    gap_before(&run[0])
+                } else {
+                    run.iter().any(gap_before)
+                };
+                if gap_above {
+                    messages_column = messages_column.push(gap_marker(state));
+                }
+                messages_column = messages_column.push(blocked_messages_bar(run, revealed));
+
+                if revealed {
+                    for (i, blocked) in run.iter().enumerate() {
+                        if i > 0 && gap_before(blocked) {
+                            messages_column = messages_column.push(gap_marker(state));
+                        }
+                        messages_column = messages_column.push(message_view(
+                            state,
+                            blocked,
+                            message_actions(state, blocked),
+                        ));
+                    }
+                }
+            }
+        }
     }
 
     let messages_scroll = scrollable(messages_column)
@@ -318,18 +769,71 @@ fn chat_view(state: &AppState) -> Element<AppMessage> {
 
     chat_content = chat_content.push(messages_scroll);
 
-    // Input
-    let input_row = row![
-        text_input("Type a message...", &state.message_input)
-            .on_input(AppMessage::MessageInputChanged)
-            .on_submit(AppMessage::SendMessage)
-            .padding(10)
-            .width(Length::Fill),
-        button("Send").on_press(AppMessage::SendMessage).padding(10),
-    ]
-    .spacing(10)
+    // Create thread form
+    if state.creating_thread_from.is_some() {
+        chat_content = chat_content.push(
+            row![
+                text("🧵").size(16),
+                text_input("Thread name", &state.thread_name_input)
+                    .on_input(AppMessage::ThreadNameInputChanged)
+                    .on_submit(AppMessage::CreateThread)
+                    .padding(8)
+                    .width(Length::Fill),
+                button(text("Create Thread").size(13))
+                    .on_press(AppMessage::CreateThread)
+                    .padding(8),
+                button(text("Cancel").size(13))
+                    .on_press(AppMessage::CancelThreadCreation)
+                    .padding(8)
+                    .style(iced::theme::Button::Secondary),
+            ]
+            .spacing(10)
+            .padding([0, 10])
+            .align_items(iced::Alignment::Center),
+        );
+    }
+
+    // Input, read-only channels get a disabled composer
+    let can_send = !state.offline
+        && !state.viewing_archive()
+        && state
+            .selected_channel_info()
+            .map(|c| state.can_send_in(c))
+            .unwrap_or(true);
+    let mut input = text_input(
+        if state.viewing_archive() {
+            "Archived channel, read-only"
+        } else if state.offline {
+            "Offline, messages can't be sent"
+        } else if can_send {
+            "Type a message..."
+        } else {
+            "You do not have permission to send messages in this channel"
+        },
+        &state.message_input,
+    )
     .padding(10)
     .width(Length::Fill);
+    let mut send = button("Send").padding(10);
+
+    // Typing stays possible during slowmode, only sending waits
+    let cooldown = state
+        .selected_channel
+        .as_ref()
+        .and_then(|id| state.slowmode_remaining(id, Instant::now()));
+    if can_send {
+        input = input.on_input(AppMessage::MessageInputChanged);
+        if cooldown.is_none() {
+            input = input.on_submit(AppMessage::SendMessage);
+            send = send.on_press(AppMessage::SendMessage);
+        }
+    }
+
+    let mut input_row = row![input].spacing(10).padding(10).width(Length::Fill);
+    if let Some(cooldown) = cooldown {
+        input_row = input_row.push(slowmode_countdown(cooldown));
+    }
+    let input_row = input_row.push(send).align_items(iced::Alignment::Center);
 
     chat_content = chat_content.push(input_row);
 
@@ -345,99 +849,1524 @@ fn chat_view(state: &AppState) -> Element<AppMessage> {
         .into()
 }
 
-fn message_view(message: &Message) -> Element<AppMessage> {
-    let author_btn = button(
-        text(format!("{}:", message.author.username))
-            .size(14)
-            .style(iced::Color::from_rgb(0.4, 0.7, 1.0)),
-    )
-    .on_press(AppMessage::ViewUserProfile(message.author.id.clone()))
-    .padding(0)
-    .style(iced::theme::Button::Text);
+fn channel_header(state: &AppState) -> Element<'_, AppMessage> {
+    let channel = state.selected_channel_info();
+    let title = match channel {
+        Some(channel) if channel.channel_type == 1 || channel.channel_type == 3 => {
+            format!("@ {}", state.channel_display_name(channel))
+        }
+        Some(channel) if channel.channel_type == 5 => {
+            format!("📢 {}", state.channel_display_name(channel))
+        }
+        Some(channel) => format!("# {}", state.channel_display_name(channel)),
+        None => "# Unknown".to_string(),
+    };
+    let in_guild = state.selected_guild.is_some() && !state.viewing_dms;
+    // Archives only have their messages, so search is all they offer
+    let archived = state.viewing_archive();
+
+    let mut header = row![text(title).size(20)]
+        .spacing(8)
+        .align_items(iced::Alignment::Center);
+
+    // Badges
+    if channel.map(|c| c.nsfw).unwrap_or(false) {
+        header = header.push(
+            container(
+                text("NSFW")
+                    .size(11)
+                    .style(iced::Color::from_rgb(1.0, 0.4, 0.4)),
+            )
+            .padding([2, 6])
+            .style(iced::theme::Container::Box),
+        );
+    }
+    if let Some(slowmode) = channel.map(|c| c.rate_limit_per_user).filter(|s| *s > 0) {
+        header = header.push(
+            container(
+                text(format!("⏱ Slowmode {}", format_duration(slowmode.into())))
+                    .size(11)
+                    .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+            )
+            .padding([2, 6])
+            .style(iced::theme::Container::Box),
+        );
+    }
+    header = header.push(iced::widget::horizontal_space());
+
+    // Actions
+    let action = |label: &'static str, message: AppMessage, active: bool| {
+        button(text(label).size(13))
+            .on_press(message)
+            .padding(6)
+            .style(if active {
+                iced::theme::Button::Primary
+            } else {
+                iced::theme::Button::Secondary
+            })
+    };
+    header = header.push(action("🔍", AppMessage::ToggleSearch, state.show_search));
+    if !archived {
+        header = header.push(action(
+            "📌",
+            AppMessage::ToggleHeaderPopover(HeaderPopover::Pins),
+            state.header_popover == Some(HeaderPopover::Pins),
+        ));
+        if in_guild {
+            header = header.push(action(
+                "🧵",
+                AppMessage::ToggleHeaderPopover(HeaderPopover::Threads),
+                state.header_popover == Some(HeaderPopover::Threads),
+            ));
+            header = header.push(action(
+                "👥",
+                AppMessage::ToggleMemberList,
+                state.show_member_list,
+            ));
+            header = header.push(action(
+                "🔔",
+                AppMessage::ToggleHeaderPopover(HeaderPopover::Notifications),
+                state.header_popover == Some(HeaderPopover::Notifications),
+            ));
+        }
+        header = header.push(action(
+            "⤓",
+            AppMessage::ToggleHeaderPopover(HeaderPopover::Export),
+            state.header_popover == Some(HeaderPopover::Export),
+        ));
+        if channel.map(|c| c.channel_type == 5).unwrap_or(false) {
+            header = header.push(action(
+                "Follow",
+                AppMessage::ToggleFollowPicker,
+                state.follow_picker_open,
+            ));
+        }
+    }
 
-    let content_text = text(&message.content).size(14);
+    let mut content = Column::new().spacing(4).push(header);
 
-    let msg_column = column![
-        row![
-            author_btn,
-            text(format_timestamp(&message.timestamp))
-                .size(12)
-                .style(iced::Color::from_rgb(0.5, 0.5, 0.5))
-        ]
-        .spacing(10),
-        content_text
-    ]
-    .spacing(2)
-    .padding(5);
+    // Topic, collapsed to its first line unless expanded
+    if let Some(topic) = channel
+        .and_then(|c| c.topic.as_deref())
+        .filter(|t| !t.trim().is_empty())
+    {
+        let topic = plain_markdown(state, topic);
+        let first_line = topic.lines().next().unwrap_or_default();
+        let truncated = topic.contains('\n') || first_line.chars().count() > TOPIC_PREVIEW_CHARS;
 
-    container(msg_column)
-        .width(Length::Fill)
-        .style(iced::theme::Container::Box)
-        .into()
-}
+        let shown = if state.topic_expanded || !truncated {
+            topic.clone()
+        } else {
+            let preview: String = first_line.chars().take(TOPIC_PREVIEW_CHARS).collect();
+            format!("{}…", preview.trim_end())
+        };
 
-fn format_timestamp(timestamp: &str) -> String {
-    // Simple timestamp formatting - just show time
-    // Discord timestamp format: 2023-01-01T12:34:56.000000+00:00
-    if let Some(time_part) = timestamp.split('T').nth(1) {
-        if let Some(time) = time_part.split('.').next() {
-            return format!(" {}", time);
+        let mut topic_row = row![text(shown)
+            .size(12)
+            .style(iced::Color::from_rgb(0.65, 0.65, 0.65))
+            .width(Length::Fill)]
+        .spacing(8);
+        if truncated {
+            topic_row = topic_row.push(
+                button(text(if state.topic_expanded { "less" } else { "more" }).size(11))
+                    .on_press(AppMessage::ToggleTopic)
+                    .padding(0)
+                    .style(iced::theme::Button::Text),
+            );
         }
+        content = content.push(topic_row);
     }
-    String::new()
-}
 
-fn render_channel<'a>(
-    channel: &'a crate::api::Channel,
-    selected_channel: &'a Option<String>,
-    indented: bool,
-) -> Element<'a, AppMessage> {
-    let channel_name = channel.name.as_deref().unwrap_or("Unknown");
-    let indent = if indented { "  " } else { "" };
+    container(content).padding(10).width(Length::Fill).into()
+}
 
-    match channel.channel_type {
-        0 => {
-            // Text channel
-            let is_selected = selected_channel
-                .as_ref()
-                .map(|id| id == &channel.id)
-                .unwrap_or(false);
+// Topics longer than this are cut off until expanded
+const TOPIC_PREVIEW_CHARS: usize = 120;
 
-            let btn = button(text(format!("{}# {}", indent, channel_name)).size(14))
-                .on_press(AppMessage::SelectChannel(channel.id.clone()))
-                .padding(8)
-                .width(Length::Fill);
+/// Renders Discord markdown as plain text: mentions become names and
+/// formatting markers are dropped, since text widgets have no styled spans.
+pub fn plain_markdown(state: &AppState, source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
 
-            let btn = if is_selected {
-                btn.style(iced::theme::Button::Primary)
-            } else {
-                btn.style(iced::theme::Button::Secondary)
-            };
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
 
-            btn.into()
-        }
-        2 => {
-            // Voice channel
-            let btn = button(
-                text(format!("{}🔊 {}", indent, channel_name))
-                    .size(14)
-                    .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
-            )
-            .padding(8)
-            .width(Length::Fill)
-            .style(iced::theme::Button::Secondary);
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..end];
+
+        let resolved = if let Some(id) = tag.strip_prefix('#') {
+            state
+                .channel_by_id(id)
+                .map(|c| format!("#{}", state.channel_display_name(c)))
+        } else if let Some(id) = tag.strip_prefix("@&") {
+            state
+                .guild_roles
+                .values()
+                .flatten()
+                .find(|r| r.id == id)
+                .map(|r| format!("@{}", r.name))
+        } else if let Some(id) = tag.strip_prefix('@') {
+            state
+                .user_cache
+                .get(id.trim_start_matches('!'))
+                .map(|u| format!("@{}", u.global_name.as_deref().unwrap_or(&u.username)))
+        } else {
+            None
+        };
 
-            btn.into()
-        }
-        _ => {
-            // Unknown - return empty container
-            container(text("")).into()
+        match resolved {
+            Some(name) => {
+                output.push_str(&name);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('<');
+                rest = &rest[1..];
+            }
         }
     }
+    output.push_str(rest);
+
+    ["**", "__", "~~", "||", "`"]
+        .iter()
+        .fold(output, |text, marker| text.replace(marker, ""))
 }
 
-fn profile_editor_view(state: &AppState) -> Element<AppMessage> {
-    let mut content = column![
+fn pins_popover(state: &AppState) -> Element<'_, AppMessage> {
+    let mut list = Column::new().spacing(6).width(Length::Fill);
+    list = list.push(
+        text("Pinned messages")
+            .size(13)
+            .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
+    );
+
+    if state.pins.is_empty() {
+        list = list.push(
+            text("This channel has no pinned messages")
+                .size(12)
+                .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        );
+    }
+
+    let can_manage = state
+        .selected_channel_info()
+        .map(|c| state.can(c, permissions::MANAGE_MESSAGES))
+        .unwrap_or(false);
+
+    for pin in &state.pins {
+        let mut actions: Vec<Element<'_, AppMessage>> = vec![button(text("Jump").size(11))
+            .on_press(AppMessage::JumpToMessage(
+                pin.channel_id.clone(),
+                pin.id.clone(),
+            ))
+            .padding(2)
+            .style(iced::theme::Button::Secondary)
+            .into()];
+        if can_manage {
+            actions.push(
+                button(text("Unpin").size(11))
+                    .on_press(AppMessage::UnpinMessage(pin.id.clone()))
+                    .padding(2)
+                    .style(iced::theme::Button::Secondary)
+                    .into(),
+            );
+        }
+        list = list.push(message_view(state, pin, actions));
+    }
+
+    container(scrollable(list))
+        .padding(10)
+        .max_height(400)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn threads_popover(state: &AppState) -> Element<'_, AppMessage> {
+    let mut list = Column::new().spacing(4).width(Length::Fill);
+    list = list.push(
+        text("Threads")
+            .size(13)
+            .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
+    );
+
+    let threads = state
+        .selected_channel
+        .as_deref()
+        .map(|id| state.threads_for(id))
+        .unwrap_or_default();
+    if threads.is_empty() {
+        list = list.push(
+            text("No threads in this channel yet")
+                .size(12)
+                .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        );
+    }
+
+    for thread in threads {
+        let mut label = format!("🧵 {}", thread.name.as_deref().unwrap_or("Thread"));
+        if thread.is_archived() {
+            label.push_str("  (archived)");
+        }
+        list = list.push(
+            button(text(label).size(13))
+                .on_press(AppMessage::OpenThread(thread.id.clone()))
+                .padding(6)
+                .width(Length::Fill)
+                .style(iced::theme::Button::Secondary),
+        );
+    }
+
+    container(scrollable(list))
+        .padding(10)
+        .max_height(300)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn notifications_popover(state: &AppState) -> Element<'_, AppMessage> {
+    let current = state
+        .selected_channel
+        .as_deref()
+        .map(|id| state.channel_notification(id));
+    let level = current
+        .as_ref()
+        .map(|c| c.message_notifications)
+        .unwrap_or(NOTIFY_INHERIT);
+    let muted = current.map(|c| c.muted).unwrap_or(false);
+
+    let mut options = Column::new().spacing(4).width(Length::Fill);
+    options = options.push(
+        text("Notification settings for this channel")
+            .size(13)
+            .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
+    );
+
+    for (value, label) in [
+        (NOTIFY_INHERIT, "Use server default"),
+        (NOTIFY_ALL_MESSAGES, "All messages"),
+        (NOTIFY_ONLY_MENTIONS, "Only @mentions"),
+        (NOTIFY_NOTHING, "Nothing"),
+    ] {
+        options = options.push(
+            button(text(label).size(13))
+                .on_press(AppMessage::SetChannelNotifications(value))
+                .padding(6)
+                .width(Length::Fill)
+                .style(if value == level {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+    }
+
+    options = options.push(
+        button(
+            text(if muted {
+                "Unmute channel"
+            } else {
+                "Mute channel"
+            })
+            .size(13),
+        )
+        .on_press(AppMessage::ToggleChannelMute)
+        .padding(6)
+        .width(Length::Fill)
+        .style(iced::theme::Button::Secondary),
+    );
+
+    container(options)
+        .padding(10)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn export_popover(state: &AppState) -> Element<'_, AppMessage> {
+    let target = state
+        .export_target
+        .as_deref()
+        .and_then(|id| state.channel_by_id(id))
+        .map(|c| state.channel_display_name(c))
+        .unwrap_or_default();
+
+    let mut content = Column::new().spacing(6).width(Length::Fill);
+    content = content.push(
+        text(format!("Export the history of {}", target))
+            .size(13)
+            .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
+    );
+
+    let mut formats = row![].spacing(6);
+    for format in ExportFormat::ALL {
+        formats = formats.push(
+            button(text(format.display_name()).size(12))
+                .on_press(AppMessage::ToggleExportFormat(format))
+                .padding(4)
+                .style(if state.export_formats.contains(&format) {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+    }
+    content = content.push(formats);
+
+    content = content.push(
+        row![
+            text_input("From YYYY-MM-DD", &state.export_since)
+                .on_input(AppMessage::ExportSinceChanged)
+                .padding(6)
+                .size(13),
+            text_input("To YYYY-MM-DD", &state.export_until)
+                .on_input(AppMessage::ExportUntilChanged)
+                .padding(6)
+                .size(13),
+        ]
+        .spacing(6),
+    );
+    content = content.push(
+        text_input("Directory", &state.export_directory)
+            .on_input(AppMessage::ExportDirectoryChanged)
+            .padding(6)
+            .size(13),
+    );
+
+    let mut export_button = button(text("Export").size(13)).padding(6);
+    if state.export_job.is_none() && !state.export_formats.is_empty() {
+        export_button = export_button.on_press(AppMessage::StartExport);
+    }
+    let mut actions = row![export_button]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+    if let Some(status) = &state.export_status {
+        actions = actions.push(
+            text(status)
+                .size(12)
+                .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+        );
+    }
+    content = content.push(actions);
+
+    container(content)
+        .padding(10)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn no_access_view<'a>(state: &AppState, channel: &Channel) -> Element<'a, AppMessage> {
+    let content = column![
+        text("🔒").size(40),
+        text(format!("#{}", state.channel_display_name(channel))).size(20),
+        text("You do not have permission to view this channel.")
+            .size(14)
+            .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+    ]
+    .spacing(10)
+    .align_items(iced::Alignment::Center);
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
+
+/// Per-message action buttons shown in the main chat.
+fn message_actions<'a>(state: &AppState, message: &'a Message) -> Vec<Element<'a, AppMessage>> {
+    let mut actions = Vec::new();
+    if state.viewing_archive() {
+        return actions;
+    }
+
+    // Threads can only be started from guild channels
+    let in_guild_channel = state
+        .selected_channel_info()
+        .map(|c| c.guild_id.is_some() || !state.viewing_dms)
+        .unwrap_or(false);
+
+    let channel = state.selected_channel_info();
+    let is_announcement = channel.map(|c| c.channel_type == 5).unwrap_or(false);
+
+    if is_announcement {
+        if message.flags & MESSAGE_FLAG_CROSSPOSTED != 0 {
+            actions.push(
+                text("Published")
+                    .size(11)
+                    .style(iced::Color::from_rgb(0.5, 0.5, 0.5))
+                    .into(),
+            );
+        } else {
+            // Anyone can publish their own posts, others' need Manage Messages
+            let is_own = state.current_user.as_ref().map(|u| &u.id) == Some(&message.author.id);
+            let can_manage = channel
+                .map(|c| state.can(c, permissions::MANAGE_MESSAGES))
+                .unwrap_or(false);
+
+            if is_own || can_manage {
+                actions.push(
+                    button(text("Publish").size(11))
+                        .on_press(AppMessage::PublishMessage(message.id.clone()))
+                        .padding(2)
+                        .style(iced::theme::Button::Secondary)
+                        .into(),
+                );
+            }
+        }
+    }
+
+    if channel
+        .map(|c| state.can(c, permissions::MANAGE_MESSAGES))
+        .unwrap_or(false)
+    {
+        let (label, on_press) = if message.pinned {
+            ("Unpin", AppMessage::UnpinMessage(message.id.clone()))
+        } else {
+            ("📌", AppMessage::PinMessage(message.id.clone()))
+        };
+        actions.push(
+            button(text(label).size(11))
+                .on_press(on_press)
+                .padding(2)
+                .style(iced::theme::Button::Text)
+                .into(),
+        );
+    }
+
+    let can_create_threads = channel
+        .map(|c| state.can(c, permissions::CREATE_PUBLIC_THREADS))
+        .unwrap_or(false);
+    if in_guild_channel && can_create_threads {
+        actions.push(
+            button(text("🧵").size(12))
+                .on_press(AppMessage::StartThreadFromMessage(message.id.clone()))
+                .padding(2)
+                .style(iced::theme::Button::Text)
+                .into(),
+        );
+    }
+
+    actions
+}
+
+fn render_voice_occupants<'a>(
+    state: &'a AppState,
+    channel: &'a Channel,
+) -> Element<'a, AppMessage> {
+    let mut column = Column::new().spacing(1).width(Length::Fill);
+
+    if channel.channel_type != 2 && channel.channel_type != 13 {
+        return column.into();
+    }
+
+    let occupants = state.voice_occupants(channel);
+
+    if let Some(limit) = channel.user_limit.filter(|l| *l > 0) {
+        column = column.push(
+            text(format!("      👥 {}/{}", occupants.len(), limit))
+                .size(11)
+                .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        );
+    }
+
+    if channel.channel_type == 13 {
+        // Stages split speakers from the (suppressed) audience
+        let (speakers, audience): (Vec<_>, Vec<_>) =
+            occupants.into_iter().partition(|v| !v.suppress);
+
+        if !speakers.is_empty() {
+            column = column.push(voice_group_label("Speakers"));
+            for occupant in speakers {
+                column = column.push(voice_occupant(state, occupant));
+            }
+        }
+        if !audience.is_empty() {
+            column = column.push(voice_group_label(&format!("Audience — {}", audience.len())));
+            for occupant in audience {
+                column = column.push(voice_occupant(state, occupant));
+            }
+        }
+    } else {
+        for occupant in occupants {
+            column = column.push(voice_occupant(state, occupant));
+        }
+    }
+
+    column.into()
+}
+
+fn voice_group_label<'a>(label: &str) -> Element<'a, AppMessage> {
+    text(format!("      {}", label.to_uppercase()))
+        .size(10)
+        .style(iced::Color::from_rgb(0.5, 0.5, 0.5))
+        .into()
+}
+
+fn voice_occupant<'a>(state: &AppState, voice_state: &VoiceState) -> Element<'a, AppMessage> {
+    let mut icons = Vec::new();
+    if voice_state.mute || voice_state.self_mute {
+        icons.push("🔇");
+    }
+    if voice_state.deaf || voice_state.self_deaf {
+        icons.push("🎧✕");
+    }
+    if voice_state.self_video {
+        icons.push("📹");
+    }
+    if voice_state.self_stream {
+        icons.push("🔴 LIVE");
+    }
+
+    row![
+        text(format!("      {}", state.voice_display_name(voice_state)))
+            .size(12)
+            .style(iced::Color::from_rgb(0.75, 0.75, 0.75))
+            .width(Length::Fill),
+        text(icons.join(" "))
+            .size(11)
+            .style(iced::Color::from_rgb(0.9, 0.4, 0.4)),
+    ]
+    .spacing(4)
+    .into()
+}
+
+fn follow_picker(state: &AppState) -> Element<'_, AppMessage> {
+    let mut picker = Column::new().spacing(6).width(Length::Fill);
+
+    picker = picker.push(
+        text("Follow this channel into a server you manage")
+            .size(13)
+            .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
+    );
+
+    let guilds = state.follow_target_guilds();
+    if guilds.is_empty() {
+        picker = picker.push(
+            text("You don't have Manage Webhooks in any other server")
+                .size(12)
+                .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        );
+    }
+
+    let mut guild_row = row![].spacing(6);
+    for guild in guilds {
+        let selected = state.follow_target_guild.as_ref() == Some(&guild.id);
+        guild_row = guild_row.push(
+            button(text(&guild.name).size(12))
+                .on_press(AppMessage::SelectFollowGuild(guild.id.clone()))
+                .padding(4)
+                .style(if selected {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+    }
+    picker = picker.push(scrollable(guild_row).direction(
+        iced::widget::scrollable::Direction::Horizontal(Default::default()),
+    ));
+
+    if state.follow_target_guild.is_some() {
+        let mut channel_row = row![].spacing(6);
+        for channel in &state.follow_target_channels {
+            channel_row = channel_row.push(
+                button(text(format!("# {}", state.channel_display_name(channel))).size(12))
+                    .on_press(AppMessage::FollowChannel(channel.id.clone()))
+                    .padding(4),
+            );
+        }
+        picker = picker.push(scrollable(channel_row).direction(
+            iced::widget::scrollable::Direction::Horizontal(Default::default()),
+        ));
+    }
+
+    container(picker)
+        .padding(10)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn render_threads<'a>(state: &'a AppState, parent: &'a Channel) -> Element<'a, AppMessage> {
+    let mut column = Column::new().spacing(2).width(Length::Fill);
+
+    // Forum posts are browsed from the post list instead
+    if parent.channel_type == 15 {
+        return column.into();
+    }
+
+    for thread in state.threads_for(&parent.id) {
+        let is_open = state.open_thread.as_ref() == Some(&thread.id);
+        let marker = if thread.is_archived() { "🗄" } else { "↳" };
+        let name = thread.name.as_deref().unwrap_or("thread");
+
+        let label = text(format!("    {} {}", marker, name)).size(13);
+        let label = if thread.is_archived() {
+            label.style(iced::Color::from_rgb(0.5, 0.5, 0.5))
+        } else {
+            label
+        };
+
+        column = column.push(
+            button(label)
+                .on_press(AppMessage::OpenThread(thread.id.clone()))
+                .padding(4)
+                .width(Length::Fill)
+                .style(if is_open {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Text
+                }),
+        );
+    }
+
+    column.into()
+}
+
+fn forum_view<'a>(state: &'a AppState, forum: &'a Channel) -> Element<'a, AppMessage> {
+    let mut content = Column::new().spacing(10).padding(10).width(Length::Fill);
+
+    content = content.push(
+        row![
+            text(format!("💬 {}", forum.name.as_deref().unwrap_or("forum")))
+                .size(20)
+                .width(Length::Fill),
+            button(
+                text(if state.show_new_post {
+                    "Cancel"
+                } else {
+                    "New Post"
+                })
+                .size(13)
+            )
+            .on_press(AppMessage::ToggleNewPostForm)
+            .padding(8),
+        ]
+        .padding(10)
+        .align_items(iced::Alignment::Center),
+    );
+
+    if state.show_new_post {
+        let mut tags_row = row![text("Tags:").size(12)]
+            .spacing(6)
+            .align_items(iced::Alignment::Center);
+        for tag in &forum.available_tags {
+            let selected = state.new_post_tags.contains(&tag.id);
+            tags_row = tags_row.push(
+                button(text(tag.label()).size(12))
+                    .on_press(AppMessage::ToggleNewPostTag(tag.id.clone()))
+                    .padding(4)
+                    .style(if selected {
+                        iced::theme::Button::Primary
+                    } else {
+                        iced::theme::Button::Secondary
+                    }),
+            );
+        }
+
+        content = content.push(
+            container(
+                column![
+                    text_input("Post title", &state.new_post_title)
+                        .on_input(AppMessage::NewPostTitleChanged)
+                        .padding(8),
+                    tags_row,
+                    text_input("First message", &state.new_post_content)
+                        .on_input(AppMessage::NewPostContentChanged)
+                        .on_submit(AppMessage::CreateForumPost)
+                        .padding(8),
+                    button(text("Post").size(13))
+                        .on_press(AppMessage::CreateForumPost)
+                        .padding(8),
+                ]
+                .spacing(8),
+            )
+            .padding(10)
+            .style(iced::theme::Container::Box),
+        );
+    }
+
+    // Sort and tag filter
+    let mut controls = row![text("Sort:").size(12)]
+        .spacing(6)
+        .padding([0, 10])
+        .align_items(iced::Alignment::Center);
+    for sort in [ForumSort::LatestActivity, ForumSort::CreationDate] {
+        controls = controls.push(
+            button(text(sort.display_name()).size(12))
+                .on_press(AppMessage::SetForumSort(sort))
+                .padding(4)
+                .style(if state.forum_sort == sort {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+    }
+
+    if !forum.available_tags.is_empty() {
+        controls = controls.push(text("  Tag:").size(12));
+        controls = controls.push(
+            button(text("All").size(12))
+                .on_press(AppMessage::SetForumTagFilter(None))
+                .padding(4)
+                .style(if state.forum_tag_filter.is_none() {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Secondary
+                }),
+        );
+        for tag in &forum.available_tags {
+            let selected = state.forum_tag_filter.as_ref() == Some(&tag.id);
+            controls = controls.push(
+                button(text(tag.label()).size(12))
+                    .on_press(AppMessage::SetForumTagFilter(Some(tag.id.clone())))
+                    .padding(4)
+                    .style(if selected {
+                        iced::theme::Button::Primary
+                    } else {
+                        iced::theme::Button::Secondary
+                    }),
+            );
+        }
+    }
+
+    content = content.push(controls);
+
+    // Posts
+    let mut posts_column = Column::new().spacing(6).padding(10);
+    let posts = state.forum_posts(&forum.id);
+
+    if posts.is_empty() {
+        posts_column = posts_column.push(
+            text("No posts yet")
+                .size(14)
+                .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        );
+    }
+
+    for post in posts {
+        let tags = post
+            .applied_tags
+            .iter()
+            .filter_map(|id| forum.available_tags.iter().find(|t| &t.id == id))
+            .map(|t| t.label())
+            .collect::<Vec<_>>()
+            .join("  ·  ");
+
+        let mut details = format!("💬 {}", post.message_count.unwrap_or(0));
+        if post.is_archived() {
+            details.push_str("  ·  Archived");
+        }
+        if !tags.is_empty() {
+            details = format!("{}  ·  {}", details, tags);
+        }
+
+        let is_open = state.open_thread.as_ref() == Some(&post.id);
+
+        posts_column = posts_column.push(
+            button(
+                column![
+                    text(post.name.as_deref().unwrap_or("Untitled")).size(15),
+                    text(details)
+                        .size(12)
+                        .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+                ]
+                .spacing(4),
+            )
+            .on_press(AppMessage::OpenThread(post.id.clone()))
+            .padding(10)
+            .width(Length::Fill)
+            .style(if is_open {
+                iced::theme::Button::Primary
+            } else {
+                iced::theme::Button::Secondary
+            }),
+        );
+    }
+
+    content = content.push(
+        scrollable(posts_column)
+            .height(Length::Fill)
+            .width(Length::Fill),
+    );
+
+    if let Some(error) = &state.error {
+        content = content
+            .push(container(text(error).style(iced::Color::from_rgb(1.0, 0.3, 0.3))).padding(10));
+    }
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn member_list_visible(state: &AppState) -> bool {
+    state.show_member_list
+        && !state.viewing_dms
+        && !state.viewing_archive()
+        && state.selected_guild.is_some()
+        && state.selected_channel.is_some()
+}
+
+fn member_list_view(state: &AppState) -> Element<'_, AppMessage> {
+    let mut content = Column::new().spacing(10).padding(10).width(240);
+
+    let (member_count, online_count) = state.member_list_counts;
+    content = content.push(text("Members").size(18));
+    content = content.push(
+        text(format!("{} online · {} total", online_count, member_count))
+            .size(11)
+            .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+    );
+
+    let guild_id = state.selected_guild.as_deref().unwrap_or_default();
+    let mut list = Column::new().spacing(4).width(Length::Fill);
+
+    if state.member_list.is_empty() {
+        list = list.push(
+            text("Loading members...")
+                .size(12)
+                .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        );
+    }
+
+    for item in &state.member_list {
+        match item {
+            Some(MemberListItem::Group(group)) => {
+                list = list.push(
+                    text(format!(
+                        "{} — {}",
+                        state.member_list_group_name(&group.id).to_uppercase(),
+                        group.count
+                    ))
+                    .size(11)
+                    .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+                );
+            }
+            Some(MemberListItem::Member(member)) => {
+                list = list.push(member_list_row(state, guild_id, member));
+            }
+            // Not synced yet, scrolling further requests it
+            None => {}
+        }
+    }
+
+    content = content.push(
+        scrollable(list)
+            .on_scroll(|viewport| AppMessage::MemberListScrolled(viewport.relative_offset().y))
+            .height(Length::Fill)
+            .width(Length::Fill),
+    );
+
+    container(content)
+        .height(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn member_list_row<'a>(
+    state: &'a AppState,
+    guild_id: &str,
+    member: &'a ListMember,
+) -> Element<'a, AppMessage> {
+    let user = member.member.user.as_ref();
+    let name = member
+        .member
+        .nick
+        .as_deref()
+        .or_else(|| user.and_then(|u| u.global_name.as_deref()))
+        .or_else(|| user.map(|u| u.username.as_str()))
+        .unwrap_or("Unknown");
+
+    let status = member
+        .presence
+        .as_ref()
+        .and_then(|p| UserStatus::from_api(&p.status));
+    let dot = status.unwrap_or(UserStatus::Invisible).color();
+    let name_color = state
+        .member_color(guild_id, &member.member.roles)
+        .unwrap_or([0.85, 0.85, 0.85]);
+    // Offline members are dimmed like in the official client
+    let alpha = if status.is_some() { 1.0 } else { 0.5 };
+
+    let mut details = Column::new()
+        .width(Length::Fill)
+        .push(text(name).size(13).style(iced::Color::from_rgba(
+            name_color[0],
+            name_color[1],
+            name_color[2],
+            alpha,
+        )));
+    if let Some(custom) = member.presence.as_ref().and_then(|p| p.custom_status()) {
+        details = details.push(
+            text(custom)
+                .size(10)
+                .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+        );
+    }
+
+    row![
+        text("●")
+            .size(12)
+            .style(iced::Color::from_rgb(dot[0], dot[1], dot[2])),
+        details,
+    ]
+    .spacing(6)
+    .align_items(iced::Alignment::Center)
+    .into()
+}
+
+fn search_panel(state: &AppState) -> Element<'_, AppMessage> {
+    let mut content = Column::new().spacing(10).padding(10).width(380);
+
+    content = content.push(
+        row![
+            text("🔍 Search").size(18).width(Length::Fill),
+            button(text("✕").size(14))
+                .on_press(AppMessage::ToggleSearch)
+                .padding(4)
+                .style(iced::theme::Button::Secondary),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center),
+    );
+
+    let scope_button = |label, local: bool| {
+        let mut btn = button(text(label).size(12)).padding(4);
+        // Only the local index is reachable offline, archives have their own
+        if state.search_local != local && !state.offline && !state.viewing_archive() {
+            btn = btn.on_press(AppMessage::ToggleSearchLocal);
+        }
+        btn.style(if state.search_local == local {
+            iced::theme::Button::Primary
+        } else {
+            iced::theme::Button::Secondary
+        })
+    };
+    content = content.push(
+        row![
+            scope_button("Server", false),
+            scope_button("Local (all cached)", true)
+        ]
+        .spacing(6),
+    );
+
+    content = content.push(
+        row![
+            text_input("Search messages...", &state.search_input)
+                .on_input(AppMessage::SearchInputChanged)
+                .on_submit(AppMessage::RunSearch)
+                .padding(8)
+                .width(Length::Fill),
+            button("Go").on_press(AppMessage::RunSearch).padding(8),
+        ]
+        .spacing(8),
+    );
+    content = content.push(
+        text("from:user  mentions:user  in:channel  has:link|file|image  before:/after:YYYY-MM-DD  pinned:true")
+            .size(10)
+            .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+    );
+
+    let status = if state.searching {
+        "Searching...".to_string()
+    } else if state.search_filters.is_some() {
+        format!(
+            "{} results{}",
+            state.search_total,
+            if state.search_local {
+                " in cached messages"
+            } else {
+                ""
+            }
+        )
+    } else {
+        String::new()
+    };
+    content = content.push(
+        text(status)
+            .size(12)
+            .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
+    );
+
+    let mut results = Column::new().spacing(8).width(Length::Fill);
+    for hit in &state.search_results {
+        let jump = button(text("Jump").size(11))
+            .on_press(AppMessage::JumpToMessage(
+                hit.channel_id.clone(),
+                hit.id.clone(),
+            ))
+            .padding(2)
+            .style(iced::theme::Button::Secondary);
+
+        results = results.push(
+            column![
+                text(state.mention_context(hit))
+                    .size(11)
+                    .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+                message_view(state, hit, vec![jump.into()]),
+            ]
+            .spacing(2),
+        );
+    }
+    content = content.push(scrollable(results).height(Length::Fill).width(Length::Fill));
+
+    // Pagination
+    let offset = state.search_offset;
+    let mut pages = row![].spacing(8).align_items(iced::Alignment::Center);
+    let mut previous = button(text("‹ Prev").size(12)).padding(4);
+    if offset > 0 && !state.searching {
+        previous = previous.on_press(AppMessage::ChangeSearchPage(
+            offset.saturating_sub(SEARCH_PAGE_SIZE),
+        ));
+    }
+    let mut next = button(text("Next ›").size(12)).padding(4);
+    if offset + SEARCH_PAGE_SIZE < state.search_total && !state.searching {
+        next = next.on_press(AppMessage::ChangeSearchPage(offset + SEARCH_PAGE_SIZE));
+    }
+    if state.search_total > 0 {
+        pages = pages
+            .push(previous)
+            .push(
+                text(format!(
+                    "Page {} of {}",
+                    offset / SEARCH_PAGE_SIZE + 1,
+                    state.search_total.div_ceil(SEARCH_PAGE_SIZE)
+                ))
+                .size(12),
+            )
+            .push(next);
+        content = content.push(pages);
+    }
+
+    container(content)
+        .height(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn thread_panel(state: &AppState) -> Element<'_, AppMessage> {
+    let mut content = Column::new().spacing(10).padding(10).width(350);
+
+    let thread = state.open_thread_info();
+    let name = thread.and_then(|t| t.name.as_deref()).unwrap_or("Thread");
+
+    content = content.push(
+        row![
+            text(format!("🧵 {}", name)).size(18).width(Length::Fill),
+            button(text("✕").size(14))
+                .on_press(AppMessage::CloseThread)
+                .padding(4)
+                .style(iced::theme::Button::Secondary),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center),
+    );
+
+    if let Some(thread) = thread {
+        let mut info = row![].spacing(8).align_items(iced::Alignment::Center);
+
+        if thread.is_archived() {
+            info = info.push(
+                text("Archived")
+                    .size(12)
+                    .style(iced::Color::from_rgb(0.9, 0.7, 0.3)),
+            );
+        }
+        if thread.is_locked() {
+            info = info.push(
+                text("🔒 Locked")
+                    .size(12)
+                    .style(iced::Color::from_rgb(0.9, 0.5, 0.5)),
+            );
+        }
+        if let Some(count) = thread.message_count {
+            info = info.push(
+                text(format!("{} messages", count))
+                    .size(12)
+                    .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+            );
+        }
+
+        let membership = if thread.member.is_some() {
+            button(text("Leave").size(12))
+                .on_press(AppMessage::LeaveThread(thread.id.clone()))
+                .style(iced::theme::Button::Secondary)
+        } else {
+            button(text("Join").size(12)).on_press(AppMessage::JoinThread(thread.id.clone()))
+        };
+        info = info.push(membership.padding(4));
+
+        content = content.push(info);
+    }
+
+    let mut messages_column = Column::new().spacing(8);
+    for message in &state.thread_messages {
+        if state.is_blocked(&message.author.id) {
+            continue;
+        }
+        messages_column = messages_column.push(message_view(state, message, Vec::new()));
+    }
+
+    content = content.push(
+        scrollable(messages_column)
+            .height(Length::Fill)
+            .width(Length::Fill),
+    );
+
+    let locked = thread.map(|t| t.is_locked()).unwrap_or(false);
+    let can_send = thread.map(|t| state.can_send_in(t)).unwrap_or(true);
+    let mut input = text_input(
+        if locked {
+            "This thread is locked"
+        } else if !can_send {
+            "You do not have permission to send messages in this thread"
+        } else {
+            "Reply in thread..."
+        },
+        &state.thread_input,
+    )
+    .padding(8)
+    .width(Length::Fill);
+    let mut send = button("Send").padding(8);
+
+    let cooldown = thread.and_then(|t| state.slowmode_remaining(&t.id, Instant::now()));
+    if can_send {
+        input = input.on_input(AppMessage::ThreadInputChanged);
+        if cooldown.is_none() {
+            input = input.on_submit(AppMessage::SendThreadMessage);
+            send = send.on_press(AppMessage::SendThreadMessage);
+        }
+    }
+
+    let mut input_row = row![input].spacing(8).align_items(iced::Alignment::Center);
+    if let Some(cooldown) = cooldown {
+        input_row = input_row.push(slowmode_countdown(cooldown));
+    }
+    content = content.push(input_row.push(send));
+
+    container(content)
+        .width(350)
+        .height(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+fn gap_marker(state: &AppState) -> Element<'_, AppMessage> {
+    let mut marker = row![text("— Some messages here haven't been loaded —")
+        .size(13)
+        .style(iced::Color::from_rgb(1.0, 0.7, 0.3))]
+    .spacing(6)
+    .padding(5)
+    .align_items(iced::Alignment::Center);
+
+    if state.backfilling {
+        marker = marker.push(
+            text("loading...")
+                .size(13)
+                .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+        );
+    } else if !state.offline {
+        marker = marker.push(
+            button(text("load").size(13))
+                .on_press(AppMessage::BackfillGap)
+                .padding(0)
+                .style(iced::theme::Button::Text),
+        );
+    }
+
+    marker.into()
+}
+
+fn blocked_messages_bar(run: &[Message], revealed: bool) -> Element<'_, AppMessage> {
+    let label = if run.len() == 1 {
+        "1 blocked message".to_string()
+    } else {
+        format!("{} blocked messages", run.len())
+    };
+
+    row![
+        text(format!("{} —", label))
+            .size(13)
+            .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+        button(text(if revealed { "hide" } else { "show" }).size(13))
+            .on_press(AppMessage::ToggleBlockedMessages(
+                run.iter().map(|m| m.id.clone()).collect()
+            ))
+            .padding(0)
+            .style(iced::theme::Button::Text),
+    ]
+    .spacing(6)
+    .padding(5)
+    .align_items(iced::Alignment::Center)
+    .into()
+}
+
+fn mentions_view(state: &AppState) -> Element<'_, AppMessage> {
+    let mut content = Column::new().spacing(10).padding(10).width(Length::Fill);
+
+    content = content.push(
+        row![
+            text("Mentions").size(20).width(Length::Fill),
+            button(text("✕").size(16))
+                .on_press(AppMessage::ToggleMentions)
+                .padding(6)
+                .style(iced::theme::Button::Secondary),
+        ]
+        .padding(10)
+        .align_items(iced::Alignment::Center),
+    );
+
+    let mut entries = Column::new().spacing(8).padding(10);
+
+    if state.mentions.is_empty() {
+        entries = entries.push(
+            text("No recent mentions")
+                .size(14)
+                .style(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        );
+    }
+
+    for mention in &state.mentions {
+        let entry = column![
+            text(state.mention_context(mention))
+                .size(12)
+                .style(iced::Color::from_rgb(0.6, 0.6, 0.6)),
+            message_view(state, mention, Vec::new()),
+            row![
+                button(text("Jump").size(12))
+                    .on_press(AppMessage::JumpToMessage(
+                        mention.channel_id.clone(),
+                        mention.id.clone()
+                    ))
+                    .padding(4),
+                button(text("Mark as read").size(12))
+                    .on_press(AppMessage::MarkMentionRead(mention.id.clone()))
+                    .padding(4)
+                    .style(iced::theme::Button::Secondary),
+            ]
+            .spacing(8),
+        ]
+        .spacing(4);
+
+        entries = entries.push(entry);
+    }
+
+    content = content.push(scrollable(entries).height(Length::Fill).width(Length::Fill));
+
+    if let Some(error) = &state.error {
+        content = content
+            .push(container(text(error).style(iced::Color::from_rgb(1.0, 0.3, 0.3))).padding(10));
+    }
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn message_view<'a>(
+    state: &AppState,
+    message: &'a Message,
+    actions: Vec<Element<'a, AppMessage>>,
+) -> Element<'a, AppMessage> {
+    let (name, color) = state.author_display(message);
+    let color = color.unwrap_or([0.4, 0.7, 1.0]);
+    let author_btn = button(
+        text(format!("{}:", name))
+            .size(14)
+            .style(iced::Color::from_rgb(color[0], color[1], color[2])),
+    )
+    .on_press(AppMessage::ViewUserProfile(message.author.id.clone()))
+    .padding(0)
+    .style(iced::theme::Button::Text);
+    let author_btn = tooltip(
+        author_btn,
+        text(&message.author.username).size(12),
+        tooltip::Position::Top,
+    )
+    .style(iced::theme::Container::Box);
+
+    // While searching, matched words are marked in every message shown
+    let content_text = if state.show_search && !state.search_terms.is_empty() {
+        text(highlight_terms(&message.content, &state.search_terms)).size(14)
+    } else {
+        text(&message.content).size(14)
+    };
+
+    let mut header = row![
+        author_btn,
+        text(format_timestamp(&message.timestamp))
+            .size(12)
+            .style(iced::Color::from_rgb(0.5, 0.5, 0.5))
+            .width(Length::Fill)
+    ]
+    .spacing(10);
+
+    for action in actions {
+        header = header.push(action);
+    }
+
+    let msg_column = column![header, content_text].spacing(2).padding(5);
+
+    container(msg_column)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Box)
+        .into()
+}
+
+/// Wraps words starting with one of the (lowercase) terms in «», as text
+/// widgets can't style part of their content.
+fn highlight_terms(content: &str, terms: &[String]) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut word = String::new();
+
+    let flush = |word: &mut String, output: &mut String| {
+        if word.is_empty() {
+            return;
+        }
+        let lower = word.to_lowercase();
+        if terms.iter().any(|t| lower.starts_with(t.as_str())) {
+            output.push('«');
+            output.push_str(word);
+            output.push('»');
+        } else {
+            output.push_str(word);
+        }
+        word.clear();
+    };
+
+    for c in content.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut output);
+            output.push(c);
+        }
+    }
+    flush(&mut word, &mut output);
+
+    output
+}
+
+fn slowmode_countdown<'a>(remaining: Duration) -> Element<'a, AppMessage> {
+    // Round up so the countdown never shows 0s while still blocked
+    let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    text(format!("⏱ {}", format_duration(seconds)))
+        .size(13)
+        .style(iced::Color::from_rgb(0.9, 0.7, 0.3))
+        .into()
+}
+
+/// Compact duration like Discord's slowmode labels: 30s, 5m, 2h, 1m 30s.
+fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let parts: Vec<String> = [(hours, "h"), (minutes, "m"), (seconds, "s")]
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+pub fn format_timestamp(timestamp: &str) -> String {
+    // Simple timestamp formatting - just show time
+    // Discord timestamp format: 2023-01-01T12:34:56.000000+00:00
+    if let Some(time_part) = timestamp.split('T').nth(1) {
+        if let Some(time) = time_part.split('.').next() {
+            return format!(" {}", time);
+        }
+    }
+    String::new()
+}
+
+fn render_channel<'a>(
+    channel: &'a crate::api::Channel,
+    selected_channel: &'a Option<String>,
+    indented: bool,
+    locked: bool,
+) -> Element<'a, AppMessage> {
+    let channel_name = channel.name.as_deref().unwrap_or("Unknown");
+    let indent = if indented { "  " } else { "" };
+    let lock = if locked { " 🔒" } else { "" };
+    let name_style = if locked {
+        iced::theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5))
+    } else {
+        iced::theme::Text::Default
+    };
+
+    match channel.channel_type {
+        0 | 5 | 15 => {
+            // Text, announcement or forum channel
+            let is_selected = selected_channel
+                .as_ref()
+                .map(|id| id == &channel.id)
+                .unwrap_or(false);
+            let glyph = match channel.channel_type {
+                15 => "💬",
+                5 => "📢",
+                _ => "#",
+            };
+
+            let btn = button(
+                text(format!("{}{} {}{}", indent, glyph, channel_name, lock))
+                    .size(14)
+                    .style(name_style),
+            )
+            .on_press(AppMessage::SelectChannel(channel.id.clone()))
+            .padding(8)
+            .width(Length::Fill);
+
+            let btn = if is_selected {
+                btn.style(iced::theme::Button::Primary)
+            } else {
+                btn.style(iced::theme::Button::Secondary)
+            };
+
+            btn.into()
+        }
+        2 | 13 => {
+            // Voice or stage channel
+            let glyph = if channel.channel_type == 13 {
+                "🎙"
+            } else {
+                "🔊"
+            };
+            let btn = button(
+                text(format!("{}{} {}{}", indent, glyph, channel_name, lock))
+                    .size(14)
+                    .style(iced::Color::from_rgb(0.7, 0.7, 0.7)),
+            )
+            .padding(8)
+            .width(Length::Fill)
+            .style(iced::theme::Button::Secondary);
+
+            btn.into()
+        }
+        _ => {
+            // Unknown - return empty container
+            container(text("")).into()
+        }
+    }
+}
+
+fn profile_editor_view(state: &AppState) -> Element<'_, AppMessage> {
+    let mut content = column![
         text("Edit Profile").size(28),
         text("").size(10),
         text("Display Name").size(14),
@@ -477,7 +2406,7 @@ fn profile_editor_view(state: &AppState) -> Element<AppMessage> {
         .into()
 }
 
-fn user_profile_modal(state: &AppState) -> Element<AppMessage> {
+fn user_profile_modal(state: &AppState) -> Element<'_, AppMessage> {
     let profile = state.viewing_user_profile.as_ref().unwrap();
     let user = &profile.user;
 
@@ -513,6 +2442,22 @@ fn user_profile_modal(state: &AppState) -> Element<AppMessage> {
     .width(400)
     .align_items(iced::Alignment::Center);
 
+    // Can't DM yourself
+    let is_self = state
+        .current_user
+        .as_ref()
+        .map(|me| me.id == user.id)
+        .unwrap_or(false);
+
+    if !is_self {
+        modal_content = modal_content.push(
+            button(text("Message").size(14))
+                .on_press(AppMessage::OpenDirectMessage(user.id.clone()))
+                .padding(8),
+        );
+        modal_content = modal_content.push(text("").size(10));
+    }
+
     // Bio section
     if let Some(bio) = &profile.bio {
         if !bio.trim().is_empty() {